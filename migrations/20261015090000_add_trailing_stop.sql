@@ -0,0 +1 @@
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS trailing_pct DECIMAL(10, 6);
@@ -0,0 +1 @@
+ALTER TABLE trades ALTER COLUMN closed_at DROP NOT NULL;
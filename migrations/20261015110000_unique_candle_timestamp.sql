@@ -0,0 +1,5 @@
+DELETE FROM candles a USING candles b
+WHERE a.ctid < b.ctid AND a.timestamp = b.timestamp;
+
+DROP INDEX IF EXISTS idx_candles_timestamp;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_candles_timestamp ON candles(timestamp);
@@ -0,0 +1,2 @@
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS adds INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS last_add_price DECIMAL(20, 8);
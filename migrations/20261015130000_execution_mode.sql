@@ -0,0 +1,2 @@
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS mode TEXT NOT NULL DEFAULT 'live';
+ALTER TABLE signals ADD COLUMN IF NOT EXISTS mode TEXT NOT NULL DEFAULT 'live';
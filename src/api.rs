@@ -0,0 +1,106 @@
+use crate::data::{BotState, ManualOrderReq, OrderReq, Signal, TradingBot};
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::info;
+use uuid::Uuid;
+
+type ApiResult<T> = std::result::Result<T, (StatusCode, String)>;
+
+fn internal(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+async fn state_handler(State(bot): State<Arc<TradingBot>>) -> ApiResult<Json<BotState>> {
+    bot.get_state().await.map(Json).map_err(internal)
+}
+
+async fn order_handler(
+    State(bot): State<Arc<TradingBot>>,
+    Json(req): Json<ManualOrderReq>,
+) -> ApiResult<(StatusCode, String)> {
+    if req.size <= Default::default() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Order size must be positive".to_string(),
+        ));
+    }
+
+    let order = OrderReq {
+        id: Uuid::new_v4().to_string(),
+        symbol: req.symbol,
+        side: req.side,
+        order_type: req.order_type,
+        time_in_force: req.time_in_force,
+        price: req.price,
+        size: req.size,
+        sl: req.sl,
+        tp: req.tp,
+        manual: true,
+    };
+    let id = order.id.clone();
+
+    order
+        .check_brackets()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    bot.place_manual_order(order)
+        .await
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    info!("Placed manual order {}", id);
+
+    Ok((StatusCode::ACCEPTED, id))
+}
+
+async fn pause_handler(State(bot): State<Arc<TradingBot>>) -> StatusCode {
+    bot.pause();
+    StatusCode::NO_CONTENT
+}
+
+#[derive(Deserialize)]
+struct SignalsQuery {
+    limit: Option<i64>,
+}
+
+async fn signals_handler(
+    State(bot): State<Arc<TradingBot>>,
+    Query(query): Query<SignalsQuery>,
+) -> ApiResult<Json<Vec<Signal>>> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    bot.db
+        .get_recent_signals(limit)
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+async fn resume_handler(State(bot): State<Arc<TradingBot>>) -> StatusCode {
+    bot.resume().await;
+    StatusCode::NO_CONTENT
+}
+
+pub fn router(bot: Arc<TradingBot>) -> Router {
+    Router::new()
+        .route("/state", get(state_handler))
+        .route("/signals", get(signals_handler))
+        .route("/order", post(order_handler))
+        .route("/pause", post(pause_handler))
+        .route("/resume", post(resume_handler))
+        .with_state(bot)
+}
+
+pub async fn serve(bot: Arc<TradingBot>, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("Control API listening on port {}", port);
+    axum::serve(listener, router(bot)).await?;
+
+    Ok(())
+}
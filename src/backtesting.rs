@@ -1,103 +1,266 @@
 use crate::{
+    config::AppConfig,
     data::{Candles, Position, PositionSide, Side},
     signal::MarketSignal,
 };
-use rust_decimal::{
-    prelude::{FromPrimitive, ToPrimitive},
-    Decimal,
-};
+use anyhow::{Context, Result};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::Serialize;
+use std::fs;
 
 pub struct BackTesting {
     pub analyzer: MarketSignal,
     pub init_amount: Decimal,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    pub slippage: Decimal,
+    pub min_confidence: Decimal,
+    pub stop_loss_pct: Decimal,
+    pub take_profit_pct: Decimal,
+    pub risk_per_trade: Decimal,
+    pub mark_to_market: bool,
     pub positions: Vec<Position>,
 }
 
+pub struct Ledger {
+    pub balance: Decimal,
+    pub total_pnl: Decimal,
+    pub total_fees: Decimal,
+    pub total_trades: u32,
+    pub winning_trades: u32,
+    pub trade_returns: Vec<f64>,
+    pub trades: Vec<BacktestTrade>,
+    pub equity_curve: Vec<(i64, Decimal)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestTrade {
+    pub id: String,
+    pub symbol: String,
+    pub side: PositionSide,
+    pub entry_price: Decimal,
+    pub exit_price: Decimal,
+    pub size: Decimal,
+    pub pnl: Decimal,
+    pub opened_at: i64,
+    pub closed_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BacktestResult {
     pub init_balance: Decimal,
     pub final_balance: Decimal,
     pub total_pnl: Decimal,
+    pub total_fees: Decimal,
     pub total_trades: u32,
     pub winning_trades: u32,
     pub losing_trades: u32,
     pub win_rate: f64,
     pub return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub sharpe_ratio: f64,
+    pub trades: Vec<BacktestTrade>,
+    pub equity_curve: Vec<(i64, Decimal)>,
 }
 
 impl BackTesting {
-    pub fn new(init_amount: Decimal) -> Self {
+    pub fn new(analyzer: MarketSignal, init_amount: Decimal, config: &AppConfig) -> Self {
+        let hundred = Decimal::new(100, 0);
+
         Self {
-            analyzer: MarketSignal::new(),
+            analyzer,
             init_amount,
+            maker_fee: config.maker_fee_pct / hundred,
+            taker_fee: config.taker_fee_pct / hundred,
+            slippage: config.slippage_bps / Decimal::new(10_000, 0),
+            min_confidence: config.min_confidence,
+            stop_loss_pct: config.stop_loss_percent / hundred,
+            take_profit_pct: config.take_profit_percent / hundred,
+            risk_per_trade: config.risk_per_trade / hundred,
+            mark_to_market: false,
             positions: Vec::new(),
         }
     }
 
+    fn fill_price(&self, price: Decimal, buying: bool) -> Decimal {
+        if buying {
+            price * (Decimal::ONE + self.slippage)
+        } else {
+            price * (Decimal::ONE - self.slippage)
+        }
+    }
+
+    fn settle(
+        &self,
+        position: &Position,
+        trigger_price: Decimal,
+        closed_at: i64,
+        ledger: &mut Ledger,
+    ) {
+        let exit_price = self.fill_price(
+            trigger_price,
+            matches!(position.position_side, PositionSide::Short),
+        );
+        let gross = match position.position_side {
+            PositionSide::Long => (exit_price - position.entry_price) * position.size,
+            PositionSide::Short => (position.entry_price - exit_price) * position.size,
+        };
+        let exit_fee = exit_price * position.size * self.maker_fee;
+        let pnl = position.realized_pnl + gross - exit_fee;
+        let cost = position.entry_price * position.size;
+
+        ledger.balance += cost + gross - exit_fee;
+        ledger.total_pnl += pnl;
+        ledger.total_fees += exit_fee;
+        ledger.total_trades += 1;
+
+        if cost > Decimal::ZERO {
+            ledger
+                .trade_returns
+                .push((pnl / cost).to_f64().unwrap_or(0.0));
+        }
+
+        if pnl > Decimal::ZERO {
+            ledger.winning_trades += 1;
+        }
+
+        ledger.trades.push(BacktestTrade {
+            id: position.id.clone(),
+            symbol: position.symbol.clone(),
+            side: position.position_side,
+            entry_price: position.entry_price,
+            exit_price,
+            size: position.size,
+            pnl,
+            opened_at: position.opened_at,
+            closed_at,
+        });
+    }
+
     pub fn run(&mut self, historical_data: Vec<Candles>, symbol: String) -> BacktestResult {
-        let mut balance = self.init_amount;
-        let mut total_pnl = Decimal::ZERO;
-        let mut total_trades = 0;
-        let mut winning_trades = 0;
+        let mut ledger = Ledger {
+            balance: self.init_amount,
+            total_pnl: Decimal::ZERO,
+            total_fees: Decimal::ZERO,
+            total_trades: 0,
+            winning_trades: 0,
+            trade_returns: Vec::new(),
+            trades: Vec::new(),
+            equity_curve: Vec::new(),
+        };
+        let mut peak_equity = self.init_amount;
+        let mut max_drawdown_pct: f64 = 0.0;
+        let mut last_candle: Option<Candles> = None;
 
         for candle in historical_data {
             self.analyzer.add_candles(candle.clone());
 
-            let mut closed_positions = Vec::new();
-
-            for (i, position) in self.positions.iter().enumerate() {
-                if candle.low <= position.stop_loss {
-                    let pnl = (position.stop_loss - position.entry_price) * position.size;
-                    total_pnl += pnl;
-                    balance += position.stop_loss * position.size;
-                    total_trades += 1;
+            if ledger.equity_curve.is_empty() {
+                ledger
+                    .equity_curve
+                    .push((candle.timestamp, self.init_amount));
+            }
 
-                    if pnl > Decimal::ZERO {
-                        winning_trades += 1;
-                    }
+            let exits: Vec<(usize, Decimal)> = self
+                .positions
+                .iter()
+                .enumerate()
+                .filter_map(|(i, position)| {
+                    let exit_price = match position.position_side {
+                        PositionSide::Long => {
+                            if candle.low <= position.stop_loss {
+                                Some(position.stop_loss)
+                            } else if candle.high >= position.take_profit {
+                                Some(position.take_profit)
+                            } else {
+                                None
+                            }
+                        }
+                        PositionSide::Short => {
+                            if candle.high >= position.stop_loss {
+                                Some(position.stop_loss)
+                            } else if candle.low <= position.take_profit {
+                                Some(position.take_profit)
+                            } else {
+                                None
+                            }
+                        }
+                    };
+                    exit_price.map(|price| (i, price))
+                })
+                .collect();
 
-                    closed_positions.push(i);
-                } else if candle.high >= position.take_profit {
-                    let pnl = (position.take_profit - position.entry_price) * position.size;
-                    total_pnl += pnl;
-                    balance += position.take_profit * position.size;
-                    total_trades += 1;
+            for (i, exit_price) in exits.iter().rev() {
+                let position = self.positions.remove(*i);
+                self.settle(&position, *exit_price, candle.timestamp, &mut ledger);
+            }
 
-                    if pnl > Decimal::ZERO {
-                        winning_trades += 1;
+            let open_value: Decimal = self
+                .positions
+                .iter()
+                .map(|p| match p.position_side {
+                    PositionSide::Long => candle.close * p.size,
+                    PositionSide::Short => {
+                        p.entry_price * p.size + (p.entry_price - candle.close) * p.size
                     }
+                })
+                .sum();
+            let equity = ledger.balance + open_value;
 
-                    closed_positions.push(i);
-                }
+            if !exits.is_empty() || self.mark_to_market {
+                ledger.equity_curve.push((candle.timestamp, equity));
             }
 
-            for i in closed_positions.iter().rev() {
-                self.positions.remove(*i);
-            }
+            peak_equity = peak_equity.max(equity);
+            max_drawdown_pct = max_drawdown_pct.max(Self::drawdown_pct(peak_equity, equity));
+
+            last_candle = Some(candle.clone());
 
             if let Some(signal) = self.analyzer.analyze(symbol.clone()) {
-                let decimal = Decimal::from_f64(0.7).unwrap();
+                let position_side = match signal.action {
+                    Side::Buy => PositionSide::Long,
+                    Side::Sell => PositionSide::Short,
+                    Side::Hold => continue,
+                };
 
-                if signal.confidence > decimal && signal.action == Side::Buy {
-                    let stop_loss = signal.price * Decimal::new(98, 2);
-                    let take_profit = signal.price * Decimal::new(104, 2);
-                    let risk_amount = balance * Decimal::new(2, 2);
-                    let risk_per_unit = signal.price - stop_loss;
+                if signal.confidence >= self.min_confidence {
+                    let (stop_loss, take_profit) = match position_side {
+                        PositionSide::Long => (
+                            signal.price * (Decimal::ONE - self.stop_loss_pct),
+                            signal.price * (Decimal::ONE + self.take_profit_pct),
+                        ),
+                        PositionSide::Short => (
+                            signal.price * (Decimal::ONE + self.stop_loss_pct),
+                            signal.price * (Decimal::ONE - self.take_profit_pct),
+                        ),
+                    };
+                    let risk_amount = ledger.balance * self.risk_per_trade;
+                    let risk_per_unit = (signal.price - stop_loss).abs();
 
                     if risk_per_unit > Decimal::ZERO {
-                        let quantity = risk_amount / risk_per_unit;
-                        let cost = signal.price * quantity;
+                        let entry_price = self
+                            .fill_price(signal.price, matches!(position_side, PositionSide::Long));
+                        let quantity = (risk_amount / risk_per_unit)
+                            .min(ledger.balance / (entry_price * (Decimal::ONE + self.taker_fee)));
+                        let cost = entry_price * quantity;
+                        let entry_fee = cost * self.taker_fee;
 
-                        if cost <= balance {
-                            balance -= cost;
+                        if quantity > Decimal::ZERO && cost + entry_fee <= ledger.balance {
+                            ledger.balance -= cost + entry_fee;
+                            ledger.total_fees += entry_fee;
                             self.positions.push(Position {
                                 id: signal.id,
                                 symbol: symbol.clone(),
-                                position_side: PositionSide::Long,
-                                entry_price: signal.price,
+                                position_side,
+                                entry_price,
                                 size: quantity,
                                 stop_loss,
                                 take_profit,
                                 opened_at: candle.timestamp,
+                                realized_pnl: -entry_fee,
+                                trailing_pct: None,
+                                adds: 0,
+                                last_add_price: None,
                             });
                         }
                     }
@@ -105,6 +268,18 @@ impl BackTesting {
             }
         }
 
+        if let Some(candle) = last_candle {
+            for position in std::mem::take(&mut self.positions) {
+                self.settle(&position, candle.close, candle.timestamp, &mut ledger);
+            }
+
+            ledger.equity_curve.push((candle.timestamp, ledger.balance));
+        }
+
+        let balance = ledger.balance;
+        let total_trades = ledger.total_trades;
+        let winning_trades = ledger.winning_trades;
+
         let win_rate = if total_trades > 0 {
             (winning_trades as f64 / total_trades as f64) * 100.0
         } else {
@@ -118,13 +293,45 @@ impl BackTesting {
         BacktestResult {
             init_balance: self.init_amount,
             final_balance: balance,
-            total_pnl,
+            total_pnl: ledger.total_pnl,
+            total_fees: ledger.total_fees,
             total_trades,
             winning_trades,
             losing_trades: total_trades - winning_trades,
             win_rate,
             return_pct,
+            max_drawdown_pct,
+            sharpe_ratio: Self::sharpe_ratio(&ledger.trade_returns),
+            trades: ledger.trades,
+            equity_curve: ledger.equity_curve,
+        }
+    }
+
+    fn drawdown_pct(peak: Decimal, equity: Decimal) -> f64 {
+        if peak <= Decimal::ZERO || equity >= peak {
+            return 0.0;
         }
+
+        ((peak - equity) / peak * Decimal::new(100, 0))
+            .to_f64()
+            .unwrap_or(0.0)
+    }
+
+    fn sharpe_ratio(returns: &[f64]) -> f64 {
+        if returns.len() < 2 {
+            return 0.0;
+        }
+
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+
+        if std_dev == 0.0 {
+            return 0.0;
+        }
+
+        mean / std_dev
     }
 }
 
@@ -134,11 +341,108 @@ impl BacktestResult {
         println!("Initial Balance:    ${}", self.init_balance);
         println!("Final Balance:      ${}", self.final_balance);
         println!("Total PnL:          ${}", self.total_pnl);
+        println!("Total Fees:         ${}", self.total_fees);
         println!("Total Trades:       {}", self.total_trades);
         println!("Winning Trades:     {}", self.winning_trades);
         println!("Losing Trades:      {}", self.losing_trades);
         println!("Win Rate:           {:.2}%", self.win_rate);
         println!("Return:             {:.2}%", self.return_pct);
+        println!("Max Drawdown:       {:.2}%", self.max_drawdown_pct);
+        println!("Sharpe Ratio:       {:.2}", self.sharpe_ratio);
         println!("======================================\n");
     }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn write_json(&self, path: &str) -> Result<()> {
+        fs::write(path, self.to_json())
+            .with_context(|| format!("Failed to write backtest results to: {}", path))
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<()> {
+        let mut csv =
+            String::from("id,symbol,side,entry_price,exit_price,size,pnl,opened_at,closed_at\n");
+
+        for trade in &self.trades {
+            csv.push_str(&format!(
+                "{},{},{:?},{},{},{},{},{},{}\n",
+                trade.id,
+                trade.symbol,
+                trade.side,
+                trade.entry_price,
+                trade.exit_price,
+                trade.size,
+                trade.pnl,
+                trade.opened_at,
+                trade.closed_at
+            ));
+        }
+
+        fs::write(path, csv).with_context(|| format!("Failed to write trade log to: {}", path))
+    }
+
+    pub fn write_equity_csv(&self, path: &str) -> Result<()> {
+        let mut csv = String::from("timestamp,equity\n");
+
+        for (timestamp, equity) in &self.equity_curve {
+            csv.push_str(&format!("{},{}\n", timestamp, equity));
+        }
+
+        fs::write(path, csv).with_context(|| format!("Failed to write equity curve to: {}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sharpe_ratio_needs_two_returns() {
+        assert_eq!(BackTesting::sharpe_ratio(&[]), 0.0);
+        assert_eq!(BackTesting::sharpe_ratio(&[0.05]), 0.0);
+    }
+
+    #[test]
+    fn sharpe_ratio_is_zero_without_variance() {
+        assert_eq!(BackTesting::sharpe_ratio(&[0.01, 0.01, 0.01]), 0.0);
+    }
+
+    #[test]
+    fn sharpe_ratio_divides_mean_by_sample_std_dev() {
+        let sharpe = BackTesting::sharpe_ratio(&[0.02, -0.01, 0.03, 0.0]);
+        let std_dev = (0.001_f64 / 3.0).sqrt();
+
+        assert!((sharpe - 0.01 / std_dev).abs() < 1e-9);
+    }
+
+    #[test]
+    fn drawdown_is_measured_from_the_peak() {
+        let peak = Decimal::new(10_000, 0);
+
+        assert_eq!(
+            BackTesting::drawdown_pct(peak, Decimal::new(9_000, 0)),
+            10.0
+        );
+        assert_eq!(
+            BackTesting::drawdown_pct(peak, Decimal::new(7_500, 0)),
+            25.0
+        );
+    }
+
+    #[test]
+    fn drawdown_is_zero_at_a_new_high_or_without_a_peak() {
+        let peak = Decimal::new(10_000, 0);
+
+        assert_eq!(BackTesting::drawdown_pct(peak, peak), 0.0);
+        assert_eq!(
+            BackTesting::drawdown_pct(peak, Decimal::new(11_000, 0)),
+            0.0
+        );
+        assert_eq!(
+            BackTesting::drawdown_pct(Decimal::ZERO, Decimal::new(-5, 0)),
+            0.0
+        );
+    }
 }
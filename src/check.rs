@@ -0,0 +1,73 @@
+use crate::{config::AppConfig, rate_limiter::RateLimiter, rest_client::BinanceClient};
+use anyhow::{anyhow, Result};
+
+fn report(name: &str, result: Result<String>) -> bool {
+    match result {
+        Ok(detail) => {
+            println!("[PASS] {}: {}", name, detail);
+            true
+        }
+        Err(e) => {
+            println!("[FAIL] {}: {}", name, e);
+            false
+        }
+    }
+}
+
+pub async fn run_check(config_path: &str) -> Result<()> {
+    let config = match AppConfig::from_file(config_path) {
+        Ok(config) => {
+            report("config", Ok(format!("loaded {}", config_path)));
+            config
+        }
+        Err(e) => {
+            report("config", Err(e));
+            return Err(anyhow!("Check failed: config could not be loaded"));
+        }
+    };
+
+    let mut passed = report(
+        "validation",
+        config
+            .validate()
+            .map(|_| "config values are valid".to_string()),
+    );
+
+    let credentials = config.credentials();
+    passed &= report(
+        "credentials",
+        credentials
+            .as_ref()
+            .map(|_| "API and secret keys present".to_string())
+            .map_err(|e| anyhow!("{}", e)),
+    );
+
+    if let Ok((api_key, secret_key)) = credentials {
+        let rate_limiter = RateLimiter::new(config.requests_per_minute, config.burst_limit);
+        let client = BinanceClient::new(api_key, secret_key, config.testnet, rate_limiter);
+
+        for symbol in &config.symbols {
+            let filters = client.fetch_symbol_filters(symbol).await.map(|filters| {
+                format!(
+                    "step {}, min qty {}, tick {}, min notional {}",
+                    filters.step_size, filters.min_qty, filters.tick_size, filters.min_notional
+                )
+            });
+            passed &= report(&format!("exchange info {}", symbol), filters);
+
+            let quote_asset = symbol.split('/').next_back().unwrap_or("USDT");
+            let balance = client
+                .account_balance(quote_asset)
+                .await
+                .map(|balance| format!("{} {}", balance, quote_asset));
+            passed &= report(&format!("account balance {}", quote_asset), balance);
+        }
+    }
+
+    if passed {
+        println!("All checks passed");
+        Ok(())
+    } else {
+        Err(anyhow!("One or more checks failed"))
+    }
+}
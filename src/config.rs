@@ -0,0 +1,408 @@
+use anyhow::{anyhow, Context, Result};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::{env, fs, sync::Arc, time::SystemTime};
+use tokio::{
+    sync::watch,
+    time::{sleep, Duration},
+};
+use tracing::{error, info, warn, Level};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Telegram,
+    Discord,
+    #[default]
+    None,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    pub level: String,
+    pub file_path: Option<String>,
+    pub max_file_size: u64,
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: "info".to_string(),
+            file_path: None,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PyramidingConfig {
+    pub enabled: bool,
+    pub max_adds: u32,
+    pub add_spacing_pct: Decimal,
+}
+
+impl Default for PyramidingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_adds: 2,
+            add_spacing_pct: Decimal::ONE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    pub rsi: usize,
+    pub ema_fast: usize,
+    pub ema_slow: usize,
+    pub trend_fast: usize,
+    pub trend_slow: usize,
+    pub min_candles: usize,
+    pub keltner_period: usize,
+    pub keltner_atr_mult: Option<Decimal>,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            rsi: 14,
+            ema_fast: 12,
+            ema_slow: 26,
+            trend_fast: 20,
+            trend_slow: 50,
+            min_candles: 50,
+            keltner_period: 20,
+            keltner_atr_mult: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub secret_key: Option<String>,
+    pub symbols: Vec<String>,
+    pub timeframe: String,
+    #[serde(default = "default_testnet")]
+    pub testnet: bool,
+    pub size: Decimal,
+    pub risk_per_trade: Decimal,
+    pub max_positions: usize,
+    #[serde(default = "default_max_positions_per_symbol")]
+    pub max_positions_per_symbol: usize,
+    pub min_confidence: Decimal,
+    pub stop_loss_percent: Decimal,
+    pub take_profit_percent: Decimal,
+    #[serde(default)]
+    pub indicators: IndicatorConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub book_depth: Option<u32>,
+    #[serde(default)]
+    pub confirm_timeframe: Option<String>,
+    #[serde(default)]
+    pub atr_stop_multiplier: Option<Decimal>,
+    #[serde(default = "default_risk_reward_ratio")]
+    pub risk_reward_ratio: Decimal,
+    #[serde(default)]
+    pub max_position_size: Option<Decimal>,
+    #[serde(default)]
+    pub max_exposure: Option<Decimal>,
+    #[serde(default)]
+    pub max_daily_loss: Option<Decimal>,
+    #[serde(default)]
+    pub max_daily_trades: Option<u32>,
+    #[serde(default)]
+    pub max_drawdown_pct: Option<Decimal>,
+    #[serde(default)]
+    pub max_consecutive_losses: Option<u32>,
+    #[serde(default = "default_circuit_breaker_cooldown_secs")]
+    pub circuit_breaker_cooldown_secs: u64,
+    #[serde(default)]
+    pub trailing_stop_percent: Option<Decimal>,
+    #[serde(default)]
+    pub pyramiding: PyramidingConfig,
+    #[serde(default)]
+    pub entry_cooldown_secs: Option<u64>,
+    #[serde(default)]
+    pub entry_offset_pct: Option<Decimal>,
+    #[serde(default)]
+    pub entry_market_fallback: bool,
+    #[serde(default)]
+    pub maker_fee_pct: Decimal,
+    #[serde(default)]
+    pub taker_fee_pct: Decimal,
+    #[serde(default)]
+    pub slippage_bps: Decimal,
+    #[serde(default)]
+    pub paper_trading: bool,
+    #[serde(skip)]
+    pub replay: bool,
+    #[serde(default)]
+    pub close_on_exit: bool,
+    #[serde(default)]
+    pub notifier: NotifierKind,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub max_alerts_per_minute: Option<u32>,
+    #[serde(default)]
+    pub metrics_port: Option<u16>,
+    #[serde(default)]
+    pub api_port: Option<u16>,
+    #[serde(default = "default_heartbeat_interval")]
+    pub heartbeat_interval: u64,
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u32,
+    #[serde(default = "default_burst_limit")]
+    pub burst_limit: u32,
+}
+
+fn default_testnet() -> bool {
+    true
+}
+
+fn default_risk_reward_ratio() -> Decimal {
+    Decimal::new(2, 0)
+}
+
+fn default_max_positions_per_symbol() -> usize {
+    1
+}
+
+fn default_circuit_breaker_cooldown_secs() -> u64 {
+    3600
+}
+
+fn default_heartbeat_interval() -> u64 {
+    30
+}
+
+fn default_requests_per_minute() -> u32 {
+    1200
+}
+
+fn default_burst_limit() -> u32 {
+    20
+}
+
+pub fn timeframe_secs(timeframe: &str) -> Option<i64> {
+    let unit = timeframe.chars().last()?;
+    let count = timeframe[..timeframe.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .ok()
+        .filter(|count| *count > 0)?;
+
+    let unit_secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    Some(count * unit_secs)
+}
+
+impl AppConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path))?;
+        let config: Self = serde_json::from_str(&raw)
+            .with_context(|| format!("Failed to parse config file: {}", path))?;
+
+        if config.symbols.is_empty() {
+            return Err(anyhow!("No symbols configured in: {}", path));
+        }
+
+        Ok(config)
+    }
+
+    pub fn credentials(&self) -> Result<(String, String)> {
+        let resolve = |var: &str, file_value: &Option<String>| -> Result<String> {
+            env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .or_else(|| file_value.clone().filter(|value| !value.trim().is_empty()))
+                .ok_or_else(|| anyhow!("{} is not set in the environment or config", var))
+        };
+
+        Ok((
+            resolve("API_KEY", &self.api_key)?,
+            resolve("SECRET_KEY", &self.secret_key)?,
+        ))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.symbols.is_empty() {
+            return Err(anyhow!("At least one symbol must be configured"));
+        }
+
+        if let Some(symbol) = self.symbols.iter().find(|s| !s.contains('/')) {
+            return Err(anyhow!("Symbol {} must be in BASE/QUOTE form", symbol));
+        }
+
+        if self.timeframe.is_empty() {
+            return Err(anyhow!("Timeframe must not be empty"));
+        }
+
+        if self.risk_per_trade <= Decimal::ZERO {
+            return Err(anyhow!("risk_per_trade must be positive"));
+        }
+
+        if self.max_positions == 0 || self.max_positions_per_symbol == 0 {
+            return Err(anyhow!(
+                "max_positions and max_positions_per_symbol must be at least 1"
+            ));
+        }
+
+        if self.stop_loss_percent <= Decimal::ZERO || self.take_profit_percent <= Decimal::ZERO {
+            return Err(anyhow!(
+                "stop_loss_percent and take_profit_percent must be positive"
+            ));
+        }
+
+        if self.min_confidence < Decimal::ZERO || self.min_confidence > Decimal::ONE {
+            return Err(anyhow!("min_confidence must be between 0 and 1"));
+        }
+
+        if self.indicators.ema_fast >= self.indicators.ema_slow
+            || self.indicators.trend_fast >= self.indicators.trend_slow
+        {
+            return Err(anyhow!(
+                "Fast indicator periods must be shorter than slow ones"
+            ));
+        }
+
+        if let Some(multiplier) = self.indicators.keltner_atr_mult {
+            if multiplier <= Decimal::ZERO {
+                return Err(anyhow!("indicators.keltner_atr_mult must be positive"));
+            }
+
+            if self.indicators.keltner_period == 0 {
+                return Err(anyhow!("indicators.keltner_period must be positive"));
+            }
+        }
+
+        if let Some(timeframe) = &self.confirm_timeframe {
+            if timeframe_secs(timeframe).is_none() {
+                return Err(anyhow!("Invalid confirm_timeframe: {}", timeframe));
+            }
+        }
+
+        if self.pyramiding.enabled && self.pyramiding.add_spacing_pct <= Decimal::ZERO {
+            return Err(anyhow!("pyramiding.add_spacing_pct must be positive"));
+        }
+
+        if self.logging.level.parse::<Level>().is_err() {
+            return Err(anyhow!("Invalid log level: {}", self.logging.level));
+        }
+
+        if self.logging.file_path.is_some()
+            && (self.logging.max_file_size == 0 || self.logging.max_files == 0)
+        {
+            return Err(anyhow!(
+                "max_file_size and max_files must be positive for file logging"
+            ));
+        }
+
+        if self.requests_per_minute == 0 || self.burst_limit == 0 {
+            return Err(anyhow!(
+                "requests_per_minute and burst_limit must be positive"
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ConfigWatcher {
+    pub path: String,
+    pub interval: Duration,
+    pub tx: watch::Sender<Arc<AppConfig>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(
+        path: String,
+        config: Arc<AppConfig>,
+        interval: Duration,
+    ) -> (Self, watch::Receiver<Arc<AppConfig>>) {
+        let (tx, rx) = watch::channel(config);
+        (Self { path, interval, tx }, rx)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    fn reload(&self) -> Result<AppConfig> {
+        let current = self.tx.borrow().clone();
+        let mut config = AppConfig::from_file(&self.path)?;
+        config.validate()?;
+
+        if config.symbols != current.symbols
+            || config.timeframe != current.timeframe
+            || config.paper_trading != current.paper_trading
+        {
+            warn!("Changes to symbols, timeframe or paper_trading require a restart, keeping the current values");
+        }
+
+        config.symbols = current.symbols.clone();
+        config.timeframe = current.timeframe.clone();
+        config.paper_trading = current.paper_trading;
+        config.replay = current.replay;
+
+        Ok(config)
+    }
+
+    pub async fn run(self) {
+        let mut last_modified = self.modified();
+
+        loop {
+            sleep(self.interval).await;
+
+            let modified = self.modified();
+
+            if modified == last_modified {
+                continue;
+            }
+
+            last_modified = modified;
+
+            match self.reload() {
+                Ok(config) => {
+                    info!("Reloaded config from {}", self.path);
+
+                    if self.tx.send(Arc::new(config)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    error!("Rejected config reload from {}: {}", self.path, e);
+                }
+            }
+        }
+    }
+}
@@ -1,40 +1,142 @@
 use crate::{
-    db::Database, position_manager::PositionManager, rest_client::BinanceClient,
-    signal::MarketSignal,
+    config::AppConfig, db::Database, notification::AlertNotifier,
+    position_manager::PositionManager, rest_client::BinanceClient, signal::MarketSignal,
 };
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use serde::Deserialize;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+};
+use tokio::sync::{mpsc, watch, RwLock};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum PositionSide {
     Long,
     Short,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Side {
     Buy,
     Sell,
     Hold,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
 pub enum OrderType {
     Market,
     Limit,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeInForce {
+    #[default]
+    Gtc,
+    Ioc,
+    Fok,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    pub fn from_binance(value: &str) -> Result<Self> {
+        match value {
+            "NEW" | "PENDING_NEW" => Ok(Self::New),
+            "PARTIALLY_FILLED" => Ok(Self::PartiallyFilled),
+            "FILLED" => Ok(Self::Filled),
+            "CANCELED" | "PENDING_CANCEL" => Ok(Self::Canceled),
+            "REJECTED" => Ok(Self::Rejected),
+            "EXPIRED" | "EXPIRED_IN_MATCH" => Ok(Self::Expired),
+            _ => Err(anyhow!("Unknown order status from Binance: {}", value)),
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Filled | Self::Canceled | Self::Rejected | Self::Expired
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Trend {
     Up,
     Down,
     Sideways,
 }
 
+impl PositionSide {
+    pub fn to_db_str(self) -> &'static str {
+        match self {
+            Self::Long => "Long",
+            Self::Short => "Short",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Result<Self> {
+        match value {
+            "Long" => Ok(Self::Long),
+            "Short" => Ok(Self::Short),
+            _ => Err(anyhow!("Unknown position side in database: {}", value)),
+        }
+    }
+}
+
+impl Side {
+    pub fn to_db_str(&self) -> &'static str {
+        match self {
+            Self::Buy => "Buy",
+            Self::Sell => "Sell",
+            Self::Hold => "Hold",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Result<Self> {
+        match value {
+            "Buy" => Ok(Self::Buy),
+            "Sell" => Ok(Self::Sell),
+            "Hold" => Ok(Self::Hold),
+            _ => Err(anyhow!("Unknown side in database: {}", value)),
+        }
+    }
+}
+
+impl Trend {
+    pub fn to_db_str(&self) -> &'static str {
+        match self {
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Sideways => "Sideways",
+        }
+    }
+
+    pub fn from_db_str(value: &str) -> Result<Self> {
+        match value {
+            "Up" => Ok(Self::Up),
+            "Down" => Ok(Self::Down),
+            "Sideways" => Ok(Self::Sideways),
+            _ => Err(anyhow!("Unknown trend in database: {}", value)),
+        }
+    }
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Position {
     pub id: String,
     pub symbol: String,
@@ -44,6 +146,24 @@ pub struct Position {
     pub stop_loss: Decimal,
     pub take_profit: Decimal,
     pub opened_at: i64,
+    pub realized_pnl: Decimal,
+    pub trailing_pct: Option<Decimal>,
+    pub adds: u32,
+    pub last_add_price: Option<Decimal>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ClosedTrade {
+    pub id: String,
+    pub symbol: String,
+    pub position_side: PositionSide,
+    pub entry_price: Decimal,
+    pub exit_price: Decimal,
+    pub size: Decimal,
+    pub pnl: Decimal,
+    pub opened_at: DateTime<Utc>,
+    pub closed_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +176,66 @@ pub struct Candles {
     pub timestamp: i64,
 }
 
+impl Candles {
+    pub fn from_csv(path: &str) -> Result<Vec<Candles>> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read candle file: {}", path))?;
+        let mut candles = Vec::new();
+
+        for (index, line) in raw.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+
+            if fields.len() < 6 {
+                return Err(anyhow!(
+                    "Expected 6 columns at line {} of {}: {}",
+                    index + 1,
+                    path,
+                    line
+                ));
+            }
+
+            let Ok(timestamp) = fields[0].parse::<i64>() else {
+                if index == 0 {
+                    continue;
+                }
+                return Err(anyhow!(
+                    "Invalid timestamp at line {} of {}: {}",
+                    index + 1,
+                    path,
+                    fields[0]
+                ));
+            };
+
+            let parse = |value: &str| {
+                Decimal::from_str(value).with_context(|| {
+                    format!("Invalid value at line {} of {}: {}", index + 1, path, value)
+                })
+            };
+
+            candles.push(Candles {
+                open: parse(fields[1])?,
+                high: parse(fields[2])?,
+                low: parse(fields[3])?,
+                close: parse(fields[4])?,
+                volume: parse(fields[5])?,
+                timestamp: if timestamp > 100_000_000_000 {
+                    timestamp / 1000
+                } else {
+                    timestamp
+                },
+            });
+        }
+
+        Ok(candles)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct OrderReq {
@@ -63,6 +243,7 @@ pub struct OrderReq {
     pub symbol: String,
     pub side: Side,
     pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
     pub price: Decimal,
     pub size: Decimal,
     pub sl: Option<Decimal>,
@@ -70,7 +251,63 @@ pub struct OrderReq {
     pub manual: bool,
 }
 
+impl OrderReq {
+    pub fn check_brackets(&self) -> Result<()> {
+        let (tp_ok, sl_ok) = match self.side {
+            Side::Buy => (
+                self.tp.is_none_or(|tp| tp > self.price),
+                self.sl.is_none_or(|sl| sl < self.price),
+            ),
+            Side::Sell => (
+                self.tp.is_none_or(|tp| tp < self.price),
+                self.sl.is_none_or(|sl| sl > self.price),
+            ),
+            Side::Hold => (true, true),
+        };
+
+        if !tp_ok {
+            return Err(anyhow!(
+                "Take profit {} is on the wrong side of price {} for a {:?} order",
+                self.tp.unwrap_or_default(),
+                self.price,
+                self.side
+            ));
+        }
+
+        if !sl_ok {
+            return Err(anyhow!(
+                "Stop loss {} is on the wrong side of price {} for a {:?} order",
+                self.sl.unwrap_or_default(),
+                self.price,
+                self.side
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct SymbolFilters {
+    pub step_size: Decimal,
+    pub min_qty: Decimal,
+    pub tick_size: Decimal,
+    pub min_notional: Decimal,
+}
+
 #[derive(Debug, Clone, PartialEq)]
+pub struct ConfidenceBreakdown {
+    pub base: f64,
+    pub rsi_component: f64,
+    pub macd_component: f64,
+    pub trend_component: f64,
+    pub volume_component: f64,
+    pub book_component: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Signal {
     pub id: String,
     pub timestamp: i64,
@@ -83,13 +320,22 @@ pub struct Signal {
 
 #[allow(dead_code)]
 pub struct TradingBot {
-    pub analyzer: Arc<RwLock<MarketSignal>>,
+    pub analyzers: Arc<RwLock<HashMap<String, MarketSignal>>>,
     pub position_manager: Arc<PositionManager>,
     pub binance_client: Arc<BinanceClient>,
     pub signal_tx: mpsc::Sender<Signal>,
     pub order_tx: mpsc::Sender<OrderReq>,
     pub account_balance: Arc<RwLock<Decimal>>,
     pub db: Arc<Database>,
+    pub config: watch::Receiver<Arc<AppConfig>>,
+    pub notifier: Option<AlertNotifier>,
+    pub paused: AtomicBool,
+    pub halted: AtomicBool,
+    pub peak_equity: RwLock<Decimal>,
+    pub equity_baseline: RwLock<Option<(Decimal, Decimal)>>,
+    pub last_prices: RwLock<HashMap<String, Decimal>>,
+    pub last_entries: RwLock<HashMap<String, i64>>,
+    pub exit_orders: RwLock<HashMap<String, (String, Decimal)>>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -106,6 +352,157 @@ pub struct BinanceKline {
     pub close: String,
     #[serde(rename = "v")]
     pub volume: String,
+    #[serde(rename = "x")]
+    pub is_closed: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceBalance {
+    pub asset: String,
+    pub free: String,
+    pub locked: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceAccount {
+    pub balances: Vec<BinanceBalance>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceOpenOrder {
+    #[serde(rename = "orderId")]
+    pub order_id: i64,
+    #[serde(rename = "clientOrderId")]
+    pub client_order_id: String,
+    pub side: String,
+    pub price: String,
+    #[serde(rename = "origQty")]
+    pub orig_qty: String,
+    #[serde(rename = "executedQty")]
+    pub executed_qty: String,
+    pub status: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OpenOrder {
+    pub order_id: i64,
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub executed_quantity: Decimal,
+    pub status: OrderStatus,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceOrder {
+    pub status: String,
+    #[serde(rename = "executedQty")]
+    pub executed_qty: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderFill {
+    pub status: OrderStatus,
+    pub executed_qty: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceFilter {
+    #[serde(rename = "filterType")]
+    pub filter_type: String,
+    #[serde(rename = "stepSize")]
+    pub step_size: Option<String>,
+    #[serde(rename = "minQty")]
+    pub min_qty: Option<String>,
+    #[serde(rename = "tickSize")]
+    pub tick_size: Option<String>,
+    #[serde(rename = "minNotional")]
+    pub min_notional: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceSymbolInfo {
+    pub symbol: String,
+    pub filters: Vec<BinanceFilter>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceExchangeInfo {
+    pub symbols: Vec<BinanceSymbolInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BotState {
+    pub paused: bool,
+    pub halted: bool,
+    pub circuit_breaker_tripped: bool,
+    pub positions: Vec<Position>,
+    pub account_balance: Decimal,
+    pub realized_pnl: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub trends: HashMap<String, Trend>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManualOrderReq {
+    pub symbol: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub price: Decimal,
+    pub size: Decimal,
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    #[serde(default)]
+    pub tp: Option<Decimal>,
+    #[serde(default)]
+    pub sl: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceDepth {
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceListenKey {
+    #[serde(rename = "listenKey")]
+    pub listen_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceExecutionReport {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub client_order_id: String,
+    #[serde(rename = "C", default)]
+    pub original_client_order_id: String,
+    #[serde(rename = "S")]
+    pub side: String,
+    #[serde(rename = "X")]
+    pub status: String,
+    #[serde(rename = "l")]
+    pub last_filled_qty: String,
+    #[serde(rename = "L")]
+    pub last_filled_price: String,
+    #[serde(rename = "z")]
+    pub cumulative_filled_qty: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OrderUpdate {
+    pub symbol: String,
+    pub client_order_id: String,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub last_filled_qty: Decimal,
+    pub last_filled_price: Decimal,
+    pub cumulative_filled_qty: Decimal,
 }
 
 #[allow(dead_code)]
@@ -120,3 +517,192 @@ pub struct BinanceKlineEvent {
     #[serde(rename = "k")]
     pub kline: BinanceKline,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_csv(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("sniper_{}_{}.csv", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn from_csv_skips_header_and_converts_millis() {
+        let path = write_csv(
+            "candles",
+            "timestamp,open,high,low,close,volume\n\
+             1700000000,100,110,90,105,12.5\n\
+             \n\
+             1700000060000,105,115,100,112,8\n",
+        );
+        let candles = Candles::from_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].timestamp, 1_700_000_000);
+        assert_eq!(candles[0].close, Decimal::new(105, 0));
+        assert_eq!(candles[0].volume, Decimal::new(125, 1));
+        assert_eq!(candles[1].timestamp, 1_700_000_060);
+        assert_eq!(candles[1].high, Decimal::new(115, 0));
+    }
+
+    #[test]
+    fn from_csv_rejects_short_rows() {
+        let path = write_csv("short", "1700000000,100,110,90,105\n");
+        let result = Candles::from_csv(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn from_csv_rejects_bad_timestamp_after_header() {
+        let path = write_csv(
+            "timestamp",
+            "1700000000,100,110,90,105,1\nyesterday,100,110,90,105,1\n",
+        );
+        let result = Candles::from_csv(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn from_csv_rejects_bad_values() {
+        let path = write_csv("values", "1700000000,100,high,90,105,1\n");
+        let result = Candles::from_csv(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn order_status_from_binance() {
+        let cases = [
+            ("NEW", OrderStatus::New),
+            ("PENDING_NEW", OrderStatus::New),
+            ("PARTIALLY_FILLED", OrderStatus::PartiallyFilled),
+            ("FILLED", OrderStatus::Filled),
+            ("CANCELED", OrderStatus::Canceled),
+            ("PENDING_CANCEL", OrderStatus::Canceled),
+            ("REJECTED", OrderStatus::Rejected),
+            ("EXPIRED", OrderStatus::Expired),
+            ("EXPIRED_IN_MATCH", OrderStatus::Expired),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(OrderStatus::from_binance(raw).unwrap(), expected);
+        }
+
+        assert!(OrderStatus::from_binance("UNKNOWN").is_err());
+    }
+
+    #[test]
+    fn order_status_is_final() {
+        assert!(!OrderStatus::New.is_final());
+        assert!(!OrderStatus::PartiallyFilled.is_final());
+        assert!(OrderStatus::Filled.is_final());
+        assert!(OrderStatus::Canceled.is_final());
+        assert!(OrderStatus::Rejected.is_final());
+        assert!(OrderStatus::Expired.is_final());
+    }
+
+    #[test]
+    fn position_side_round_trips_through_db_strings() {
+        for side in [PositionSide::Long, PositionSide::Short] {
+            assert_eq!(PositionSide::from_db_str(side.to_db_str()).unwrap(), side);
+        }
+
+        assert!(PositionSide::from_db_str("long").is_err());
+    }
+
+    #[test]
+    fn side_round_trips_through_db_strings() {
+        for side in [Side::Buy, Side::Sell, Side::Hold] {
+            assert_eq!(Side::from_db_str(side.to_db_str()).unwrap(), side);
+        }
+
+        assert!(Side::from_db_str("BUY").is_err());
+    }
+
+    #[test]
+    fn trend_round_trips_through_db_strings() {
+        for trend in [Trend::Up, Trend::Down, Trend::Sideways] {
+            assert_eq!(Trend::from_db_str(trend.to_db_str()).unwrap(), trend);
+        }
+
+        assert!(Trend::from_db_str("Flat").is_err());
+    }
+
+    #[test]
+    fn manual_order_time_in_force_defaults_to_gtc() {
+        let req: ManualOrderReq = serde_json::from_str(
+            r#"{"symbol":"ETH/USDT","side":"Buy","order_type":"Limit","price":"2500","size":"0.1"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(req.time_in_force, TimeInForce::Gtc);
+    }
+
+    #[test]
+    fn manual_order_reads_ioc_and_fok() {
+        for (raw, expected) in [("IOC", TimeInForce::Ioc), ("FOK", TimeInForce::Fok)] {
+            let req: ManualOrderReq = serde_json::from_str(&format!(
+                r#"{{"symbol":"ETH/USDT","side":"Sell","order_type":"Limit","price":"2500","size":"0.1","time_in_force":"{}"}}"#,
+                raw
+            ))
+            .unwrap();
+
+            assert_eq!(req.time_in_force, expected);
+        }
+    }
+
+    fn bracket_order(side: Side, tp: Option<i64>, sl: Option<i64>) -> OrderReq {
+        OrderReq {
+            id: "m1".to_string(),
+            symbol: "ETH/USDT".to_string(),
+            side,
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::Gtc,
+            price: Decimal::from(2500),
+            size: Decimal::ONE,
+            sl: sl.map(Decimal::from),
+            tp: tp.map(Decimal::from),
+            manual: true,
+        }
+    }
+
+    #[test]
+    fn buy_brackets_need_tp_above_and_sl_below_price() {
+        assert!(bracket_order(Side::Buy, Some(2600), Some(2400))
+            .check_brackets()
+            .is_ok());
+        assert!(bracket_order(Side::Buy, None, None)
+            .check_brackets()
+            .is_ok());
+        assert!(bracket_order(Side::Buy, Some(2400), None)
+            .check_brackets()
+            .is_err());
+        assert!(bracket_order(Side::Buy, None, Some(2600))
+            .check_brackets()
+            .is_err());
+        assert!(bracket_order(Side::Buy, Some(2500), None)
+            .check_brackets()
+            .is_err());
+    }
+
+    #[test]
+    fn sell_brackets_need_tp_below_and_sl_above_price() {
+        assert!(bracket_order(Side::Sell, Some(2400), Some(2600))
+            .check_brackets()
+            .is_ok());
+        assert!(bracket_order(Side::Sell, Some(2600), None)
+            .check_brackets()
+            .is_err());
+        assert!(bracket_order(Side::Sell, None, Some(2400))
+            .check_brackets()
+            .is_err());
+    }
+}
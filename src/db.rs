@@ -1,4 +1,4 @@
-use crate::data::{Candles, Position, PositionSide, Signal};
+use crate::data::{Candles, ClosedTrade, Position, PositionSide, Side, Signal, Trend};
 use anyhow::Context;
 use anyhow::Result;
 use chrono::{DateTime, TimeZone, Utc};
@@ -7,6 +7,7 @@ use sqlx::PgPool;
 
 pub struct Database {
     pub pool: PgPool,
+    pub mode: &'static str,
 }
 
 impl Database {
@@ -19,7 +20,24 @@ impl Database {
 
         sqlx::migrate!("./migrations").run(&pool).await?;
 
-        Ok(Self { pool })
+        Ok(Self { pool, mode: "live" })
+    }
+
+    pub fn with_mode(self, mode: &'static str) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub async fn clear_mode(&self) -> Result<()> {
+        sqlx::query("DELETE FROM trades WHERE mode = $1")
+            .bind(self.mode)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM signals WHERE mode = $1")
+            .bind(self.mode)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
     }
 
     pub async fn save_order(&self, position: &Position, manual: bool) -> Result<()> {
@@ -29,19 +47,22 @@ impl Database {
         sqlx::query!(
             r#"
             INSERT INTO trades (trade_id, symbol, side, entry_price, quantity,
-            stop_loss, take_profit, opened_at, status, manual)
-            VAlUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)               
+            stop_loss, take_profit, opened_at, status, manual, trailing_pct, pnl, mode)
+            VAlUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             "#,
             position.id,
             position.symbol,
-            format!("{:?}", position.position_side),
+            position.position_side.to_db_str(),
             position.entry_price,
             position.size,
             position.stop_loss,
             position.take_profit,
             opened_at,
             "open",
-            manual
+            manual,
+            position.trailing_pct,
+            position.realized_pnl,
+            self.mode
         )
         .execute(&self.pool)
         .await?;
@@ -73,22 +94,81 @@ impl Database {
         Ok(())
     }
 
+    pub async fn update_order_size(
+        &self,
+        trade_id: &str,
+        quantity: Decimal,
+        pnl: Decimal,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE trades
+            SET quantity = $1, pnl = $2
+            WHERE trade_id = $3
+            "#,
+        )
+        .bind(quantity)
+        .bind(pnl)
+        .bind(trade_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_pyramid(&self, position: &Position) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE trades
+            SET entry_price = $1, quantity = $2, pnl = $3, adds = $4, last_add_price = $5
+            WHERE trade_id = $6
+            "#,
+        )
+        .bind(position.entry_price)
+        .bind(position.size)
+        .bind(position.realized_pnl)
+        .bind(position.adds as i32)
+        .bind(position.last_add_price)
+        .bind(&position.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_stop_loss(&self, trade_id: &str, stop_loss: Decimal) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE trades
+            SET stop_loss = $1
+            WHERE trade_id = $2
+            "#,
+        )
+        .bind(stop_loss)
+        .bind(trade_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn save_signal(&self, signal: Signal) -> Result<()> {
         let ts = signal.timestamp;
         let timestamp: DateTime<Utc> = Utc.timestamp_opt(ts, 0).single().unwrap();
 
         sqlx::query!(
             r#"
-            INSERT INTO signals (id, timestamp, symbol, action, price, confidence, trend)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO signals (id, timestamp, symbol, action, price, confidence, trend, mode)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
             signal.id,
             timestamp,
             signal.symbol,
-            format!("{:?}", signal.action),
+            signal.action.to_db_str(),
             signal.price,
             signal.confidence,
-            format!("{:?}", signal.trend)
+            signal.trend.to_db_str(),
+            self.mode
         )
         .execute(&self.pool)
         .await?;
@@ -96,6 +176,48 @@ impl Database {
         Ok(())
     }
 
+    pub async fn get_recent_signals(&self, limit: i64) -> Result<Vec<Signal>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                DateTime<Utc>,
+                String,
+                String,
+                Decimal,
+                Decimal,
+                String,
+            ),
+        >(
+            r#"
+            SELECT id, timestamp, symbol, action, price, confidence, trend
+            FROM signals
+            WHERE mode = $1
+            ORDER BY timestamp DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(self.mode)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch recent signals")?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Signal {
+                    id: row.0,
+                    timestamp: row.1.timestamp(),
+                    symbol: row.2,
+                    action: Side::from_db_str(&row.3)?,
+                    price: row.4,
+                    confidence: row.5,
+                    trend: Trend::from_db_str(&row.6)?,
+                })
+            })
+            .collect()
+    }
+
     pub async fn get_open_orders(&self) -> Result<Vec<Position>> {
         let query = sqlx::query_as::<
             _,
@@ -108,45 +230,142 @@ impl Database {
                 Decimal,
                 Decimal,
                 DateTime<Utc>,
+                Decimal,
+                Option<Decimal>,
+                i32,
+                Option<Decimal>,
             ),
         >(
             r#"
             SELECT trade_id, symbol, side, entry_price, quantity, 
-            stop_loss, take_profit, opened_at
+            stop_loss, take_profit, opened_at, COALESCE(pnl, 0), trailing_pct,
+            adds, last_add_price
             FROM trades 
-            WHERE status = 'open'
+            WHERE status = 'open' AND mode = $1
             "#,
         )
+        .bind(self.mode)
         .fetch_all(&self.pool)
         .await
-        .unwrap();
+        .context("Failed to fetch open orders")?;
 
         let position = query
             .into_iter()
-            .map(|row| Position {
-                id: row.0,
-                symbol: row.1,
-                position_side: if row.2 == "Long" {
-                    PositionSide::Long
-                } else {
-                    PositionSide::Short
-                },
-                entry_price: row.3,
-                size: row.4,
-                stop_loss: row.5,
-                take_profit: row.6,
-                opened_at: row.7.timestamp(),
+            .map(|row| {
+                Ok(Position {
+                    id: row.0,
+                    symbol: row.1,
+                    position_side: PositionSide::from_db_str(&row.2)?,
+                    entry_price: row.3,
+                    size: row.4,
+                    stop_loss: row.5,
+                    take_profit: row.6,
+                    opened_at: row.7.timestamp(),
+                    realized_pnl: row.8,
+                    trailing_pct: row.9,
+                    adds: row.10.max(0) as u32,
+                    last_add_price: row.11,
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(position)
     }
 
+    pub async fn get_closed_trades(&self, since: DateTime<Utc>) -> Result<Vec<ClosedTrade>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                String,
+                Decimal,
+                Decimal,
+                Decimal,
+                Decimal,
+                DateTime<Utc>,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            SELECT trade_id, symbol, side, entry_price, COALESCE(exit_price, 0), quantity,
+            COALESCE(pnl, 0), opened_at, closed_at
+            FROM trades
+            WHERE status = 'closed' AND closed_at >= $1 AND mode = $2
+            ORDER BY closed_at
+            "#,
+        )
+        .bind(since)
+        .bind(self.mode)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch closed trades")?;
+
+        let trades = rows
+            .into_iter()
+            .map(|row| {
+                Ok(ClosedTrade {
+                    id: row.0,
+                    symbol: row.1,
+                    position_side: PositionSide::from_db_str(&row.2)?,
+                    entry_price: row.3,
+                    exit_price: row.4,
+                    size: row.5,
+                    pnl: row.6,
+                    opened_at: row.7,
+                    closed_at: row.8,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(trades)
+    }
+
+    pub async fn total_realized_pnl(&self) -> Result<Decimal> {
+        let total = sqlx::query_scalar::<_, Decimal>(
+            r#"
+            SELECT COALESCE(SUM(pnl), 0)
+            FROM trades
+            WHERE status = 'closed' AND mode = $1
+            "#,
+        )
+        .bind(self.mode)
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to sum realized pnl")?;
+
+        Ok(total)
+    }
+
+    pub async fn save_candles(&self, candles: &[Candles]) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO candles (timestamp, open, high, low, close, volume)
+            SELECT to_timestamp(t), o, h, l, c, v
+            FROM UNNEST($1::BIGINT[], $2::NUMERIC[], $3::NUMERIC[], $4::NUMERIC[],
+            $5::NUMERIC[], $6::NUMERIC[]) AS rows(t, o, h, l, c, v)
+            ON CONFLICT (timestamp) DO NOTHING
+            "#,
+        )
+        .bind(candles.iter().map(|c| c.timestamp).collect::<Vec<i64>>())
+        .bind(candles.iter().map(|c| c.open).collect::<Vec<Decimal>>())
+        .bind(candles.iter().map(|c| c.high).collect::<Vec<Decimal>>())
+        .bind(candles.iter().map(|c| c.low).collect::<Vec<Decimal>>())
+        .bind(candles.iter().map(|c| c.close).collect::<Vec<Decimal>>())
+        .bind(candles.iter().map(|c| c.volume).collect::<Vec<Decimal>>())
+        .execute(&self.pool)
+        .await
+        .context("Failed to save candles")?;
+
+        Ok(())
+    }
+
     pub async fn load_from_db(&self) -> Result<Vec<Candles>> {
         let query = sqlx::query_as::<_, (i64, Decimal, Decimal, Decimal, Decimal, Decimal)>(
             r#"
-            SELECT timestamp, open, high, low, close, volume
+            SELECT EXTRACT(EPOCH FROM timestamp)::BIGINT, open, high, low, close, volume
             FROM candles
+            ORDER BY timestamp
             "#,
         )
         .fetch_all(&self.pool)
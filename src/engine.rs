@@ -1,16 +1,38 @@
 use crate::{
-    data::{Candles, OrderReq, OrderType, Position, PositionSide, Side, Signal, TradingBot},
+    config::{timeframe_secs, AppConfig},
+    data::{
+        BotState, Candles, OrderFill, OrderReq, OrderStatus, OrderType, OrderUpdate, Position,
+        PositionSide, Side, Signal, TimeInForce, TradingBot,
+    },
     db::Database,
-    position_manager::PositionManager,
+    metrics::METRICS,
+    notification::{AlertNotifier, Notifier},
+    position_manager::{round_to_lot, round_to_tick, PositionManager},
     rest_client::BinanceClient,
     signal::MarketSignal,
+    websocket::UserDataClient,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Utc;
+use futures_util::{pin_mut, StreamExt};
 use rust_decimal::Decimal;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    sync::{mpsc, watch, RwLock},
+    time::{sleep, Duration},
+};
 use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const FILL_POLL_ATTEMPTS: u32 = 10;
+const FILL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const LISTEN_KEY_KEEPALIVE: Duration = Duration::from_secs(30 * 60);
 
 impl TradingBot {
     pub fn new(
@@ -19,156 +41,797 @@ impl TradingBot {
         initial_balance: Decimal,
         binance_client: Arc<BinanceClient>,
         db: Arc<Database>,
+        config_rx: watch::Receiver<Arc<AppConfig>>,
+        notifier: Option<AlertNotifier>,
     ) -> Result<Self> {
-        let position_manager = Arc::new(PositionManager::new(Decimal::new(2, 2), db.clone()));
+        let config = config_rx.borrow().clone();
+        let position_manager = Arc::new(PositionManager::new(&config, db.clone()));
         Ok(Self {
-            analyzer: Arc::new(RwLock::new(MarketSignal::new())),
+            analyzers: Arc::new(RwLock::new(HashMap::new())),
             position_manager,
             signal_tx,
             order_tx,
             binance_client,
             account_balance: Arc::new(RwLock::new(initial_balance)),
             db,
+            config: config_rx,
+            notifier,
+            paused: AtomicBool::new(false),
+            halted: AtomicBool::new(false),
+            peak_equity: RwLock::new(Decimal::ZERO),
+            equity_baseline: RwLock::new(None),
+            last_prices: RwLock::new(HashMap::new()),
+            last_entries: RwLock::new(HashMap::new()),
+            exit_orders: RwLock::new(HashMap::new()),
         })
     }
 
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        info!("Trading paused, no new entries will be opened");
+    }
+
+    pub async fn resume(&self) {
+        if self.halted.swap(false, Ordering::SeqCst) {
+            *self.peak_equity.write().await = Decimal::ZERO;
+            *self.equity_baseline.write().await = None;
+            info!("Drawdown halt cleared, equity peak reset");
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        info!("Trading resumed");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted.load(Ordering::SeqCst)
+    }
+
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.config.borrow().clone()
+    }
+
+    pub async fn watch_config(self: Arc<Self>) {
+        let mut config_rx = self.config.clone();
+        let mut indicators = self.config().indicators.clone();
+
+        while config_rx.changed().await.is_ok() {
+            let config = config_rx.borrow_and_update().clone();
+            self.position_manager.update_risk(&config).await;
+
+            if config.indicators != indicators {
+                for analyzer in self.analyzers.write().await.values_mut() {
+                    analyzer.apply_indicators(&config.indicators);
+                }
+                indicators = config.indicators.clone();
+                info!("Applied reloaded indicator periods");
+            }
+
+            info!("Applied reloaded config");
+        }
+    }
+
+    pub async fn notify(&self, msg: &str) {
+        if let Some(notifier) = &self.notifier {
+            if let Err(e) = notifier.notify(msg).await {
+                warn!("Failed to send notification: {}", e);
+            }
+        }
+    }
+
     pub async fn initializer(&self) -> Result<()> {
         self.position_manager.load_open_orders().await?;
+
+        if !self.config().paper_trading {
+            if let Err(e) = self
+                .position_manager
+                .reconcile(&self.binance_client, &self.config())
+                .await
+            {
+                warn!("Failed to reconcile positions with the exchange: {}", e);
+            }
+        }
+
         Ok(())
     }
 
+    pub async fn refresh_account_balance(&self, asset: &str) {
+        match self.binance_client.account_balance(asset).await {
+            Ok(balance) => {
+                info!("Account balance: {} {}", balance, asset);
+                *self.account_balance.write().await = balance;
+            }
+            Err(e) => {
+                error!("Failed to get account balance: {}", e);
+            }
+        }
+    }
+
+    pub async fn backfill_candles(&self, symbol: &str, candles: Vec<Candles>) {
+        let mut analyzers = self.analyzers.write().await;
+        let analyzer = analyzers
+            .entry(symbol.to_string())
+            .or_insert_with(|| MarketSignal::from_config(&self.config().indicators));
+        let last_timestamp = analyzer.candles.last().map(|c| c.timestamp);
+        let mut added = 0;
+
+        for candle in candles {
+            if last_timestamp.is_none_or(|ts| candle.timestamp > ts) {
+                analyzer.add_candles(candle);
+                added += 1;
+            }
+        }
+
+        info!("Backfilled {} candles for: {}", added, symbol);
+    }
+
     pub async fn process_candle(&self, candle: Candles, symbol: &str) -> Result<()> {
+        let book_pressure = match self.config().book_depth {
+            Some(_) if self.config().replay => None,
+            Some(depth) => match self
+                .binance_client
+                .order_book_imbalance(symbol, depth)
+                .await
+            {
+                Ok(imbalance) => Some(imbalance),
+                Err(e) => {
+                    warn!("Failed to fetch order book for {}: {}", symbol, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let config = self.config();
+        let higher_update = match &config.confirm_timeframe {
+            Some(_) if config.replay => None,
+            Some(timeframe) => {
+                let period = timeframe_secs(timeframe).map(|secs| candle.timestamp / secs);
+                let cached = self
+                    .analyzers
+                    .read()
+                    .await
+                    .get(symbol)
+                    .and_then(|analyzer| analyzer.higher_period);
+
+                if period.is_some() && period == cached {
+                    None
+                } else {
+                    let limit = config
+                        .indicators
+                        .trend_slow
+                        .max(config.indicators.min_candles) as u32;
+
+                    match self
+                        .binance_client
+                        .recent_klines(symbol, timeframe, limit)
+                        .await
+                    {
+                        Ok(candles) => Some((period, Some(candles))),
+                        Err(e) => {
+                            warn!(
+                                "Failed to fetch {} candles for {}, suppressing entries until they load: {}",
+                                timeframe, symbol, e
+                            );
+                            Some((None, None))
+                        }
+                    }
+                }
+            }
+            None => None,
+        };
+
         {
-            let mut analyzer = self.analyzer.write().await;
+            let mut analyzers = self.analyzers.write().await;
+            let analyzer = analyzers
+                .entry(symbol.to_string())
+                .or_insert_with(|| MarketSignal::from_config(&config.indicators));
             analyzer.add_candles(candle.clone());
+            analyzer.set_book_pressure(book_pressure);
+
+            match higher_update {
+                Some((period, candles)) => analyzer.set_higher_timeframe(period, candles),
+                None if config.confirm_timeframe.is_none() => analyzer.clear_higher_timeframe(),
+                None => {}
+            }
         }
 
+        self.last_prices
+            .write()
+            .await
+            .insert(symbol.to_string(), candle.close);
+
         let position_to_close = self
             .position_manager
             .check_positions(candle.close, symbol)
             .await;
 
-        if !position_to_close.is_empty() {
-            for (position_id, current_price, position_side) in position_to_close {
-                if let Some(position) = self
-                    .position_manager
-                    .get_positions_by_id(&position_id)
+        for (position_id, current_price, position_side) in position_to_close {
+            if self
+                .exit_orders
+                .read()
+                .await
+                .values()
+                .any(|(id, _)| *id == position_id)
+            {
+                warn!(
+                    "Exit order for {} is still unconfirmed, not sending another",
+                    position_id
+                );
+                continue;
+            }
+
+            if let Some(position) = self
+                .position_manager
+                .get_positions_by_id(&position_id)
+                .await
+            {
+                let exit_side = match position_side {
+                    PositionSide::Long => Side::Sell,
+                    PositionSide::Short => Side::Buy,
+                };
+
+                let exit_reason = match position_side {
+                    PositionSide::Long if current_price <= position.stop_loss => "Stop loss",
+                    PositionSide::Short if current_price >= position.stop_loss => "Stop loss",
+                    _ => "Take profit",
+                };
+
+                let order_type = OrderType::Limit;
+                let exit_id = format!("x{}", Uuid::new_v4().simple());
+                let req = OrderReq {
+                    id: exit_id.clone(),
+                    symbol: symbol.to_string(),
+                    side: exit_side,
+                    price: current_price,
+                    size: position.size,
+                    order_type: order_type.clone(),
+                    time_in_force: TimeInForce::default(),
+                    sl: None,
+                    tp: None,
+                    manual: false,
+                };
+
+                self.exit_orders
+                    .write()
                     .await
-                {
-                    let exit_side = match position_side {
-                        PositionSide::Long => Side::Sell,
-                        PositionSide::Short => Side::Buy,
-                    };
-
-                    let req = OrderReq {
-                        id: position_id.to_string(),
-                        symbol: symbol.to_string(),
-                        side: exit_side,
-                        price: current_price,
-                        size: position.size,
-                        order_type: OrderType::Limit,
-                        sl: None,
-                        tp: None,
-                        manual: false,
-                    };
-
-                    match self.execute_order(req).await {
-                        Ok(_) => {
-                            info!("Order succeeded, closing position...");
-                            self.position_manager
-                                .close_positions(&position_id, current_price)
-                                .await?;
-                        }
-                        Err(e) => {
-                            error!("Failed to place order: {}", e);
+                    .insert(exit_id.clone(), (position_id.clone(), Decimal::ZERO));
+
+                let filled = match self.execute_order(req.clone()).await {
+                    Ok(_) => self.await_fill(&req).await,
+                    Err(e) => Err(e),
+                };
+                let settled = filled.as_ref().map_or(true, |fill| fill.status.is_final());
+
+                match filled {
+                    Ok(fill) if fill.executed_qty > Decimal::ZERO => {
+                        if let Err(e) = self
+                            .apply_exit_fill(
+                                &exit_id,
+                                fill.executed_qty,
+                                current_price,
+                                &order_type,
+                            )
+                            .await
+                        {
+                            error!("Failed to record exit fill for {}: {}", position_id, e);
+                        } else if let Some(remaining) = self
+                            .position_manager
+                            .get_positions_by_id(&position_id)
+                            .await
+                        {
+                            warn!(
+                                "Exit order {} for {} ended {:?} with {} filled, keeping {} open",
+                                exit_id,
+                                position_id,
+                                fill.status,
+                                fill.executed_qty,
+                                remaining.size
+                            );
+                        } else {
+                            info!(
+                                "Exit order {} filled, position {} closed",
+                                exit_id, position_id
+                            );
+                            self.notify(&format!(
+                                "{} hit for {:?} position {} on {} @ {}",
+                                exit_reason, position_side, position_id, symbol, current_price
+                            ))
+                            .await;
                         }
                     }
+                    Ok(fill) => {
+                        warn!(
+                            "Exit order {} for {} ended as {:?}, keeping the position open",
+                            exit_id, position_id, fill.status
+                        );
+                    }
+                    Err(e) => {
+                        error!("Failed to place order: {}", e);
+                    }
                 }
 
-                let analyzer = self.analyzer.read().await;
-                let signal_opt = analyzer.analyze(symbol.to_string());
+                if settled {
+                    self.exit_orders.write().await.remove(&exit_id);
+                }
+            }
+        }
 
-                if let Some(signal) = signal_opt {
-                    if let Err(e) = self.db.save_signal(signal.clone()).await {
-                        warn!("Failed to save signal onto database: {}", e);
-                    }
+        if let Err(e) = self.check_drawdown().await {
+            error!("Failed to enforce max drawdown: {}", e);
+        }
+
+        let signal_opt = {
+            let analyzers = self.analyzers.read().await;
+            analyzers
+                .get(symbol)
+                .and_then(|analyzer| analyzer.analyze_detailed(symbol.to_string()))
+        };
+
+        if let Some((signal, breakdown)) = signal_opt {
+            info!(
+                "Confidence for {}: rsi {:.2}, macd {:.2}, trend {:.2}, volume {:.2}, book {:.2}, total {:.2}",
+                symbol,
+                breakdown.rsi_component,
+                breakdown.macd_component,
+                breakdown.trend_component,
+                breakdown.volume_component,
+                breakdown.book_component,
+                breakdown.total
+            );
 
-                    if let Err(e) = self.signal_tx.send(signal.clone()).await {
-                        warn!("Failed to send order: {}", e)
+            if let Err(e) = self.db.save_signal(signal.clone()).await {
+                warn!("Failed to save signal onto database: {}", e);
+            }
+
+            if let Err(e) = self.signal_tx.send(signal.clone()).await {
+                warn!("Failed to send order: {}", e)
+            }
+
+            if self.is_paused() || self.is_halted() {
+                info!("Trading is paused, skipping entries for: {}", symbol);
+                return Ok(());
+            }
+
+            if signal.confidence >= self.config().min_confidence {
+                match self.try_pyramid(&signal).await {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => {
+                        error!("Failed to add to position on {}: {}", symbol, e);
+                        return Ok(());
                     }
+                }
 
-                    let confidence_threahold = Decimal::new(70, 2);
-
-                    if signal.confidence >= confidence_threahold {
-                        match signal.action {
-                            Side::Buy => {
-                                if let Err(e) = self
-                                    .execute_entry_order(signal, position_side, OrderType::Market)
-                                    .await
-                                {
-                                    error!("Failed to place buy order for market price: {}", e);
-                                }
-                            }
-                            Side::Sell => {
-                                if let Err(e) = self
-                                    .execute_entry_order(signal, position_side, OrderType::Market)
-                                    .await
-                                {
-                                    error!("Failed to place sell order for market price: {}", e);
-                                }
-                            }
-                            Side::Hold => {
-                                info!(
-                                    "Unclear trend detected, so holding the positions for now..."
-                                );
-                            }
+                match signal.action {
+                    Side::Buy => {
+                        if let Err(e) = self.execute_entry_order(signal, OrderType::Market).await {
+                            error!("Failed to place buy order for market price: {}", e);
                         }
                     }
+                    Side::Sell => {
+                        if let Err(e) = self.execute_entry_order(signal, OrderType::Market).await {
+                            error!("Failed to place sell order for market price: {}", e);
+                        }
+                    }
+                    Side::Hold => {
+                        info!("Unclear trend detected, so holding the positions for now...");
+                    }
                 }
             }
         }
+
         Ok(())
     }
 
-    /*pub async fn place_manual_order(&self, order: OrderReq) -> Result<()> {
+    pub async fn unrealized_pnl(&self) -> Decimal {
+        let prices = self.last_prices.read().await.clone();
+        self.position_manager.unrealized_pnl(&prices).await
+    }
+
+    pub async fn get_state(&self) -> Result<BotState> {
+        let trends = self
+            .analyzers
+            .read()
+            .await
+            .iter()
+            .map(|(symbol, analyzer)| (symbol.clone(), analyzer.detect_trend()))
+            .collect();
+
+        Ok(BotState {
+            paused: self.is_paused(),
+            halted: self.is_halted(),
+            circuit_breaker_tripped: self
+                .position_manager
+                .circuit_breaker
+                .read()
+                .await
+                .is_tripped(),
+            positions: self.position_manager.position.read().await.clone(),
+            account_balance: *self.account_balance.read().await,
+            realized_pnl: self.db.total_realized_pnl().await?,
+            unrealized_pnl: self.unrealized_pnl().await,
+            trends,
+        })
+    }
+
+    pub async fn place_manual_order(&self, order: OrderReq) -> Result<()> {
         let mut manual_order = order;
         manual_order.manual = true;
+
+        let position_side = match manual_order.side {
+            Side::Buy => PositionSide::Long,
+            Side::Sell => PositionSide::Short,
+            Side::Hold => return Err(anyhow!("Cannot place a manual hold order")),
+        };
+
+        if manual_order.size <= Decimal::ZERO || manual_order.price <= Decimal::ZERO {
+            return Err(anyhow!("Manual order size and price must be positive"));
+        }
+
+        manual_order.check_brackets()?;
+
+        if !self.position_manager.can_trade().await {
+            return Err(anyhow!("Daily loss or trade limit reached"));
+        }
+
+        self.position_manager
+            .reserve_capacity(&manual_order.symbol)
+            .await?;
+
+        let symbol = manual_order.symbol.clone();
+        let result = self.submit_manual_order(manual_order, position_side).await;
+        self.position_manager.release_capacity(&symbol).await;
+        result
+    }
+
+    async fn submit_manual_order(
+        &self,
+        mut manual_order: OrderReq,
+        position_side: PositionSide,
+    ) -> Result<()> {
+        let config = self.config();
+
+        if let Some(max_size) = *self.position_manager.max_position_size.read().await {
+            if manual_order.size > max_size {
+                return Err(anyhow!(
+                    "Manual order size {} exceeds max position size {}",
+                    manual_order.size,
+                    max_size
+                ));
+            }
+        }
+
+        if let Some(max_exposure) = config.max_exposure {
+            let prices = self
+                .exposure_prices(&manual_order.symbol, manual_order.price)
+                .await;
+            let exposure = self.position_manager.total_exposure(&prices).await;
+            let notional = manual_order.size * manual_order.price;
+
+            if exposure + notional > max_exposure {
+                return Err(anyhow!(
+                    "Manual order notional {} would exceed max exposure {} (current {})",
+                    notional,
+                    max_exposure,
+                    exposure
+                ));
+            }
+        }
+
+        let take_profit_distance =
+            manual_order.price * config.take_profit_percent / Decimal::new(100, 0);
+        let stop_loss_distance =
+            manual_order.price * config.stop_loss_percent / Decimal::new(100, 0);
+        let (take_profit, stop_loss) = match position_side {
+            PositionSide::Long => (
+                manual_order
+                    .tp
+                    .unwrap_or(manual_order.price + take_profit_distance),
+                manual_order
+                    .sl
+                    .unwrap_or(manual_order.price - stop_loss_distance),
+            ),
+            PositionSide::Short => (
+                manual_order
+                    .tp
+                    .unwrap_or(manual_order.price - take_profit_distance),
+                manual_order
+                    .sl
+                    .unwrap_or(manual_order.price + stop_loss_distance),
+            ),
+        };
+        manual_order.tp = Some(take_profit);
+        manual_order.sl = Some(stop_loss);
+
+        self.execute_order(manual_order.clone()).await?;
+
+        let filled = match manual_order.order_type {
+            OrderType::Market => manual_order.size,
+            _ => self.await_fill(&manual_order).await?.executed_qty,
+        };
+
+        if filled.is_zero() {
+            return Err(anyhow!("Manual order {} did not fill", manual_order.id));
+        }
+
+        let entry_fee =
+            self.position_manager
+                .fee(&manual_order.order_type, manual_order.price, filled);
+
+        let position = Position {
+            id: manual_order.id.clone(),
+            symbol: manual_order.symbol.clone(),
+            entry_price: manual_order.price,
+            size: filled,
+            position_side,
+            opened_at: Utc::now().timestamp(),
+            realized_pnl: -entry_fee,
+            trailing_pct: config
+                .trailing_stop_percent
+                .map(|pct| pct / Decimal::new(100, 0)),
+            take_profit,
+            stop_loss,
+            adds: 0,
+            last_add_price: None,
+        };
+
+        self.position_manager.open_position(position, true).await?;
         self.order_tx.send(manual_order).await?;
         info!("Placed manual order!");
         Ok(())
-    }*/
+    }
+
+    async fn try_pyramid(&self, signal: &Signal) -> Result<bool> {
+        let config = self.config();
+
+        if !config.pyramiding.enabled {
+            return Ok(false);
+        }
+
+        let position_side = match signal.action {
+            Side::Buy => PositionSide::Long,
+            Side::Sell => PositionSide::Short,
+            Side::Hold => return Ok(false),
+        };
+
+        let Some(position) = self
+            .position_manager
+            .position
+            .read()
+            .await
+            .iter()
+            .find(|p| p.symbol == signal.symbol && p.position_side == position_side)
+            .cloned()
+        else {
+            return Ok(false);
+        };
 
-    pub async fn execute_entry_order(
+        if position.adds >= config.pyramiding.max_adds {
+            info!(
+                "Position {} already has {} adds, not pyramiding further",
+                position.id, position.adds
+            );
+            return Ok(true);
+        }
+
+        let reference = position.last_add_price.unwrap_or(position.entry_price);
+        let spacing = config.pyramiding.add_spacing_pct / Decimal::new(100, 0);
+        let moved_favorably = match position_side {
+            PositionSide::Long => signal.price >= reference * (Decimal::ONE + spacing),
+            PositionSide::Short => signal.price <= reference * (Decimal::ONE - spacing),
+        };
+
+        if !moved_favorably {
+            return Ok(true);
+        }
+
+        if !self.position_manager.can_trade().await {
+            warn!(
+                "Daily loss or trade limit reached, skipping add for: {}",
+                signal.symbol
+            );
+            return Ok(true);
+        }
+
+        let account_balance = *self.account_balance.read().await;
+        let mut add_size = self
+            .position_manager
+            .calculate_position_size(account_balance, signal.price, position.stop_loss)
+            .await;
+
+        if let Some(max_size) = *self.position_manager.max_position_size.read().await {
+            add_size = add_size.min(max_size - position.size);
+        }
+
+        if let Some(max_exposure) = config.max_exposure {
+            let prices = self.exposure_prices(&signal.symbol, signal.price).await;
+            let exposure = self.position_manager.total_exposure(&prices).await;
+            add_size = add_size.min((max_exposure - exposure) / signal.price);
+        }
+
+        if let Some(filters) = self.binance_client.cached_filters(&signal.symbol).await {
+            add_size = round_to_lot(add_size, signal.price, &filters);
+        }
+
+        if add_size <= Decimal::ZERO {
+            info!(
+                "No room to add to position {} on {}",
+                position.id, signal.symbol
+            );
+            return Ok(true);
+        }
+
+        let order = OrderReq {
+            id: format!("a{}", Uuid::new_v4().simple()),
+            symbol: signal.symbol.clone(),
+            side: signal.action.clone(),
+            price: signal.price,
+            size: add_size,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::default(),
+            tp: None,
+            sl: None,
+            manual: false,
+        };
+
+        self.execute_order(order).await?;
+        self.position_manager
+            .add_to_position(&position.id, add_size, signal.price, &OrderType::Market)
+            .await?;
+        self.notify(&format!(
+            "Added {} to {:?} position {} on {} @ {}",
+            add_size, position_side, position.id, signal.symbol, signal.price
+        ))
+        .await;
+
+        Ok(true)
+    }
+
+    pub async fn execute_entry_order(&self, signal: Signal, order_type: OrderType) -> Result<()> {
+        let position_side = match signal.action {
+            Side::Buy => PositionSide::Long,
+            Side::Sell => PositionSide::Short,
+            Side::Hold => return Err(anyhow!("Cannot open a position on a hold signal")),
+        };
+
+        if !self.position_manager.can_trade().await {
+            warn!(
+                "Daily loss or trade limit reached, skipping entry for: {}",
+                signal.symbol
+            );
+            return Ok(());
+        }
+
+        if let Err(e) = self.position_manager.reserve_capacity(&signal.symbol).await {
+            info!("Skipping entry: {}", e);
+            return Ok(());
+        }
+
+        let symbol = signal.symbol.clone();
+        let result = self.place_entry(signal, position_side, order_type).await;
+        self.position_manager.release_capacity(&symbol).await;
+        result
+    }
+
+    async fn place_entry(
         &self,
         signal: Signal,
         position_side: PositionSide,
         order_type: OrderType,
     ) -> Result<()> {
+        if let Some(cooldown) = self.config().entry_cooldown_secs {
+            if let Some(last_entry) = self.last_entries.read().await.get(&signal.symbol) {
+                if signal.timestamp - last_entry < cooldown as i64 {
+                    info!(
+                        "Entry cooldown of {}s active, skipping entry for: {}",
+                        cooldown, signal.symbol
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
+        let (mut entry_price, order_type) = match self.config().entry_offset_pct {
+            Some(offset) if offset > Decimal::ZERO => {
+                let offset = offset / Decimal::new(100, 0);
+                let price = match position_side {
+                    PositionSide::Long => signal.price * (Decimal::ONE - offset),
+                    PositionSide::Short => signal.price * (Decimal::ONE + offset),
+                };
+                (price, OrderType::Limit)
+            }
+            _ => (signal.price, order_type),
+        };
+
+        let filters = self.binance_client.cached_filters(&signal.symbol).await;
+
+        if let Some(filters) = &filters {
+            entry_price = round_to_tick(entry_price, filters.tick_size);
+        }
+
         let account_balance = *self.account_balance.read().await;
 
+        let atr = match self.config().atr_stop_multiplier {
+            Some(_) => self
+                .analyzers
+                .read()
+                .await
+                .get(&signal.symbol)
+                .and_then(|analyzer| analyzer.calculate_atr()),
+            None => None,
+        };
+
+        let (take_profit_distance, stop_loss_distance) =
+            match (self.config().atr_stop_multiplier, atr) {
+                (Some(multiplier), Some(atr)) if atr > Decimal::ZERO => {
+                    let stop_distance = atr * multiplier;
+                    (
+                        stop_distance * self.config().risk_reward_ratio,
+                        stop_distance,
+                    )
+                }
+                _ => (
+                    entry_price * self.config().take_profit_percent / Decimal::new(100, 0),
+                    entry_price * self.config().stop_loss_percent / Decimal::new(100, 0),
+                ),
+            };
+
         let (take_profit, stop_loss) = match position_side {
             PositionSide::Long => (
-                signal.price * Decimal::new(104, 2),
-                signal.price * Decimal::new(98, 2),
+                entry_price + take_profit_distance,
+                entry_price - stop_loss_distance,
             ),
             PositionSide::Short => (
-                signal.price * Decimal::new(96, 2),
-                signal.price * Decimal::new(102, 2),
+                entry_price - take_profit_distance,
+                entry_price + stop_loss_distance,
             ),
         };
 
-        let position_size = self
+        let mut position_size = self
             .position_manager
-            .calculate_position_size(account_balance, signal.price, stop_loss)
+            .calculate_position_size(account_balance, entry_price, stop_loss)
             .await;
 
+        if let Some(max_exposure) = self.config().max_exposure {
+            let prices = self.exposure_prices(&signal.symbol, entry_price).await;
+
+            if self
+                .position_manager
+                .exceeds_max_exposure(&prices, max_exposure)
+                .await
+            {
+                warn!(
+                    "Max exposure of {} reached, skipping entry for: {}",
+                    max_exposure, signal.symbol
+                );
+                return Ok(());
+            }
+
+            if entry_price > Decimal::ZERO {
+                let exposure = self.position_manager.total_exposure(&prices).await;
+                position_size = position_size.min((max_exposure - exposure) / entry_price);
+            }
+        }
+
+        if let Some(filters) = &filters {
+            position_size = round_to_lot(position_size, entry_price, filters);
+        }
+
+        let entry_fee = self
+            .position_manager
+            .fee(&order_type, entry_price, position_size);
+
         let order = OrderReq {
             id: signal.id.clone(),
             symbol: signal.symbol.clone(),
             side: signal.action.clone(),
-            price: signal.price,
+            price: entry_price,
             size: position_size,
             order_type,
+            time_in_force: TimeInForce::default(),
             tp: Some(take_profit),
             sl: Some(stop_loss),
             manual: false,
@@ -177,28 +840,61 @@ impl TradingBot {
         let position = Position {
             id: signal.id.clone(),
             symbol: signal.symbol.clone(),
-            entry_price: signal.price,
+            entry_price,
             size: position_size,
             position_side,
             opened_at: Utc::now().timestamp(),
+            realized_pnl: -entry_fee,
+            trailing_pct: self
+                .config()
+                .trailing_stop_percent
+                .map(|pct| pct / Decimal::new(100, 0)),
             take_profit,
             stop_loss,
+            adds: 0,
+            last_add_price: None,
         };
 
         if position_size <= Decimal::ZERO {
-            self.binance_client.cancel_orders(&order).await?;
-            error!("Invalid position size, cancelling the order...");
-        }
-
-        if order.tp.is_none() || order.sl.is_none() {
-            self.binance_client.cancel_orders(&order).await?;
-            error!("Take profit and stop loss is not set, cancelling the order...");
+            info!(
+                "Position size for {} rounds below the exchange minimums, skipping entry",
+                signal.symbol
+            );
+            return Ok(());
         }
 
-        match self.execute_order(order).await {
+        match self.execute_order(order.clone()).await {
             Ok(_) => {
+                let filled = self.await_entry_fill(&order, signal.price).await?;
+                if filled.is_zero() {
+                    return Ok(());
+                }
+
+                let position = Position {
+                    size: filled,
+                    realized_pnl: -self.position_manager.fee(
+                        &order.order_type,
+                        entry_price,
+                        filled,
+                    ),
+                    ..position
+                };
+                let fill_msg = format!(
+                    "Opened {:?} position {} on {}: size {} @ {}",
+                    position.position_side,
+                    position.id,
+                    position.symbol,
+                    position.size,
+                    position.entry_price
+                );
+
                 self.position_manager.open_position(position, false).await?;
+                self.last_entries
+                    .write()
+                    .await
+                    .insert(signal.symbol.clone(), signal.timestamp);
                 info!("Position opened successfully!");
+                self.notify(&fill_msg).await;
             }
             Err(e) => {
                 warn!("Failed to execute order: {}", e);
@@ -208,15 +904,428 @@ impl TradingBot {
         Ok(())
     }
 
-    pub async fn execute_order(&self, order: OrderReq) -> Result<()> {
-        if matches!(order.order_type, OrderType::Limit) {
-            self.binance_client.place_limit_order(&order).await?;
-            println!("Placed limit order for: {}", order.id);
-        } else if matches!(order.order_type, OrderType::Market) {
-            self.binance_client.place_market_order(&order).await?;
-            println!("Placed market order for: {}", order.id);
+    async fn await_entry_fill(&self, order: &OrderReq, market_price: Decimal) -> Result<Decimal> {
+        if !matches!(order.order_type, OrderType::Limit) {
+            return Ok(order.size);
+        }
+
+        let fill = self.await_fill(order).await?;
+        if matches!(fill.status, OrderStatus::Filled) {
+            return Ok(fill.executed_qty);
+        }
+
+        if !self.config().entry_market_fallback {
+            warn!(
+                "Entry limit order {} ended {:?} with {} filled, skipping the remainder",
+                order.id, fill.status, fill.executed_qty
+            );
+            return Ok(fill.executed_qty);
+        }
+
+        let remainder = order.size - fill.executed_qty;
+        warn!(
+            "Entry limit order {} ended {:?} with {} filled, falling back to market for {}",
+            order.id, fill.status, fill.executed_qty, remainder
+        );
+
+        let market_order = OrderReq {
+            id: format!("m{}", Uuid::new_v4().simple()),
+            order_type: OrderType::Market,
+            price: market_price,
+            size: remainder,
+            ..order.clone()
+        };
+
+        match self.execute_order(market_order).await {
+            Ok(_) => Ok(order.size),
+            Err(e) => {
+                warn!(
+                    "Market fallback for {} failed, keeping {} filled: {}",
+                    order.id, fill.executed_qty, e
+                );
+                Ok(fill.executed_qty)
+            }
+        }
+    }
+
+    async fn exposure_prices(&self, symbol: &str, price: Decimal) -> HashMap<String, Decimal> {
+        let mut prices = self.last_prices.read().await.clone();
+        prices.insert(symbol.to_string(), price);
+        prices
+    }
+
+    async fn market_exit_order(&self, position: &Position) -> OrderReq {
+        let exit_side = match position.position_side {
+            PositionSide::Long => Side::Sell,
+            PositionSide::Short => Side::Buy,
+        };
+
+        let price = self
+            .analyzers
+            .read()
+            .await
+            .get(&position.symbol)
+            .and_then(|analyzer| analyzer.candles.last())
+            .map(|candle| candle.close)
+            .unwrap_or(position.entry_price);
+
+        OrderReq {
+            id: position.id.clone(),
+            symbol: position.symbol.clone(),
+            side: exit_side,
+            price,
+            size: position.size,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::default(),
+            sl: None,
+            tp: None,
+            manual: false,
+        }
+    }
+
+    pub async fn flatten_positions(&self) -> Result<()> {
+        let positions = self.position_manager.position.read().await.clone();
+
+        for position in positions {
+            let req = self.market_exit_order(&position).await;
+            let price = req.price;
+
+            match self.execute_order(req).await {
+                Ok(_) => {
+                    self.position_manager
+                        .close_positions(&position.id, price, &OrderType::Market)
+                        .await?;
+                }
+                Err(e) => {
+                    error!("Failed to flatten position {}: {}", position.id, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_drawdown(&self) -> Result<()> {
+        let Some(max_drawdown) = self.config().max_drawdown_pct else {
+            return Ok(());
+        };
+
+        if self.is_halted() {
+            return Ok(());
+        }
+
+        let prices = self.last_prices.read().await.clone();
+        let closed_pnl = *self.position_manager.closed_pnl.read().await;
+        let (baseline, closed_at_baseline) = *self
+            .equity_baseline
+            .write()
+            .await
+            .get_or_insert((*self.account_balance.read().await, closed_pnl));
+        let equity = baseline
+            + (closed_pnl - closed_at_baseline)
+            + self.position_manager.open_pnl(&prices).await;
+
+        let peak = {
+            let mut peak = self.peak_equity.write().await;
+            if equity > *peak {
+                *peak = equity;
+            }
+            *peak
+        };
+
+        if peak <= Decimal::ZERO {
+            return Ok(());
+        }
+
+        let drawdown = (peak - equity) / peak * Decimal::new(100, 0);
+
+        if drawdown >= max_drawdown {
+            error!(
+                "Drawdown {:.2}% from peak equity {} exceeds {}%, flattening and halting",
+                drawdown, peak, max_drawdown
+            );
+            self.halted.store(true, Ordering::SeqCst);
+            self.flatten_positions().await?;
+            self.notify(&format!(
+                "Max drawdown hit: equity {} is {:.2}% below peak {}. All positions flattened, trading halted until resumed",
+                equity, drawdown, peak
+            ))
+            .await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn shutdown(&self) -> Result<()> {
+        let positions = self.position_manager.position.read().await.clone();
+        info!("Shutting down with {} open positions", positions.len());
+
+        if self.config().paper_trading {
+            info!("Paper trading: no resting orders to cancel");
+        } else {
+            for symbol in &self.config().symbols {
+                let orders = match self.binance_client.open_orders(symbol).await {
+                    Ok(orders) => orders,
+                    Err(e) => {
+                        warn!("Failed to fetch open orders for {}: {}", symbol, e);
+                        continue;
+                    }
+                };
+
+                for order in orders {
+                    if let Err(e) = self
+                        .binance_client
+                        .cancel_client_order(symbol, &order.client_order_id)
+                        .await
+                    {
+                        warn!(
+                            "Failed to cancel order {} on {}: {}",
+                            order.client_order_id, symbol, e
+                        );
+                    }
+                }
+            }
+        }
+
+        for position in positions {
+            let req = self.market_exit_order(&position).await;
+            let price = req.price;
+
+            if self.config().close_on_exit {
+                match self.execute_order(req).await {
+                    Ok(_) => {
+                        self.position_manager
+                            .close_positions(&position.id, price, &OrderType::Market)
+                            .await?;
+                    }
+                    Err(e) => {
+                        error!("Failed to close position {} on exit: {}", position.id, e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn on_order_update(&self, update: OrderUpdate) {
+        info!(
+            "Order update for {} on {}: {:?} {:?}, filled {} @ {}",
+            update.client_order_id,
+            update.symbol,
+            update.side,
+            update.status,
+            update.cumulative_filled_qty,
+            update.last_filled_price
+        );
+
+        if matches!(
+            update.status,
+            OrderStatus::Filled | OrderStatus::PartiallyFilled
+        ) {
+            if let Err(e) = self
+                .apply_exit_fill(
+                    &update.client_order_id,
+                    update.cumulative_filled_qty,
+                    update.last_filled_price,
+                    &OrderType::Limit,
+                )
+                .await
+            {
+                error!(
+                    "Failed to apply fill for order {}: {}",
+                    update.client_order_id, e
+                );
+            }
+
+            self.notify(&format!(
+                "{:?} {:?} order {} on {}: {} filled @ {}",
+                update.status,
+                update.side,
+                update.client_order_id,
+                update.symbol,
+                update.cumulative_filled_qty,
+                update.last_filled_price
+            ))
+            .await;
+        }
+
+        if update.status.is_final() {
+            self.exit_orders
+                .write()
+                .await
+                .remove(&update.client_order_id);
+        }
+    }
+
+    pub async fn run_user_stream(self: Arc<Self>) {
+        loop {
+            let listen_key = match self.binance_client.start_user_stream().await {
+                Ok(key) => key,
+                Err(e) => {
+                    warn!("Failed to start user data stream: {}", e);
+                    sleep(Duration::from_secs(30)).await;
+                    continue;
+                }
+            };
+
+            let bot = self.clone();
+            let key = listen_key.clone();
+            let keepalive = tokio::spawn(async move {
+                loop {
+                    sleep(LISTEN_KEY_KEEPALIVE).await;
+
+                    if let Err(e) = bot.binance_client.keepalive_user_stream(&key).await {
+                        warn!("Failed to keep user data stream alive: {}", e);
+                    }
+                }
+            });
+
+            let client = UserDataClient::new(
+                &self.binance_client.stream_url,
+                &listen_key,
+                Duration::from_secs(self.config().heartbeat_interval.max(1)),
+            );
+
+            match client.connect().await {
+                Ok(stream) => {
+                    pin_mut!(stream);
+
+                    while let Some(update) = stream.next().await {
+                        match update {
+                            Ok(update) => self.on_order_update(update).await,
+                            Err(e) => {
+                                warn!("{}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!("{}", e),
+            }
+
+            keepalive.abort();
+            warn!("User data stream ended, reconnecting...");
+            sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    pub async fn await_fill(&self, order: &OrderReq) -> Result<OrderFill> {
+        if self.config().paper_trading {
+            return Ok(OrderFill {
+                status: OrderStatus::Filled,
+                executed_qty: order.size,
+            });
+        }
+
+        let mut fill = OrderFill {
+            status: OrderStatus::New,
+            executed_qty: Decimal::ZERO,
+        };
+
+        for _ in 0..FILL_POLL_ATTEMPTS {
+            match self
+                .binance_client
+                .query_order(&order.symbol, &order.id)
+                .await
+            {
+                Ok(polled) if polled.status.is_final() => return Ok(polled),
+                Ok(polled) => fill = polled,
+                Err(e) => warn!("Failed to poll order {}: {}", order.id, e),
+            }
+
+            sleep(FILL_POLL_INTERVAL).await;
+        }
+
+        warn!(
+            "Order {} still {:?} after polling, cancelling the remainder",
+            order.id, fill.status
+        );
+
+        if let Err(e) = self.cancel_order(order).await {
+            warn!("Failed to cancel order {}: {}", order.id, e);
+        }
+
+        for _ in 0..FILL_POLL_ATTEMPTS {
+            match self
+                .binance_client
+                .query_order(&order.symbol, &order.id)
+                .await
+            {
+                Ok(fill) => return Ok(fill),
+                Err(e) => warn!("Failed to re-query order {} after cancel: {}", order.id, e),
+            }
+
+            sleep(FILL_POLL_INTERVAL).await;
+        }
+
+        error!(
+            "Could not confirm the state of order {}, using last known fill {}",
+            order.id, fill.executed_qty
+        );
+        Ok(fill)
+    }
+
+    async fn apply_exit_fill(
+        &self,
+        exit_id: &str,
+        cumulative_qty: Decimal,
+        price: Decimal,
+        order_type: &OrderType,
+    ) -> Result<()> {
+        let (position_id, filled) = {
+            let mut exits = self.exit_orders.write().await;
+            let Some((position_id, applied)) = exits.get_mut(exit_id) else {
+                return Ok(());
+            };
+
+            if cumulative_qty <= *applied {
+                return Ok(());
+            }
+
+            let filled = cumulative_qty - *applied;
+            *applied = cumulative_qty;
+            (position_id.clone(), filled)
+        };
+
+        self.position_manager
+            .reduce_position(&position_id, filled, price, order_type)
+            .await
+    }
+
+    pub async fn execute_order(&self, order: OrderReq) -> Result<String> {
+        if self.config().paper_trading {
+            info!(
+                "Paper trading: simulated {:?} {:?} fill for {} of size {} @ {}",
+                order.order_type, order.side, order.symbol, order.size, order.price
+            );
+            METRICS.record_order_placed();
+            return Ok(format!("paper-{}", order.id));
+        }
+
+        let response = match order.order_type {
+            OrderType::Limit => self.binance_client.place_limit_order(&order).await,
+            OrderType::Market => self.binance_client.place_market_order(&order).await,
+        };
+
+        match &response {
+            Ok(_) => {
+                METRICS.record_order_placed();
+                info!("Placed {:?} order for: {}", order.order_type, order.id);
+            }
+            Err(_) => METRICS.record_order_rejected(),
+        }
+
+        response
+    }
+
+    pub async fn cancel_order(&self, order: &OrderReq) -> Result<()> {
+        if self.config().paper_trading {
+            info!("Paper trading: simulated cancel for: {}", order.id);
+            return Ok(());
         }
 
+        self.binance_client.cancel_orders(order).await?;
         Ok(())
     }
 }
@@ -0,0 +1,110 @@
+use crate::config::{LogFormat, LoggingConfig};
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    sync::Mutex,
+};
+use tracing::Level;
+
+pub struct RotatingFile {
+    pub path: String,
+    pub max_size: u64,
+    pub max_files: usize,
+    pub file: File,
+    pub size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &str, max_size: u64, max_files: usize) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log file: {}", path))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_string(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let _ = fs::remove_file(format!("{}.{}", self.path, self.max_files));
+
+        for i in (1..self.max_files).rev() {
+            let from = format!("{}.{}", self.path, i);
+
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", self.path, i + 1))?;
+            }
+        }
+
+        if self.max_files > 0 {
+            fs::rename(&self.path, format!("{}.1", self.path))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+pub fn init(config: &LoggingConfig) -> Result<()> {
+    let level = config
+        .level
+        .parse::<Level>()
+        .map_err(|_| anyhow!("Invalid log level: {}", config.level))?;
+    let builder = tracing_subscriber::fmt().with_max_level(level);
+
+    match &config.file_path {
+        Some(path) => {
+            let writer = Mutex::new(RotatingFile::open(
+                path,
+                config.max_file_size,
+                config.max_files,
+            )?);
+            let builder = builder.with_writer(writer).with_ansi(false);
+
+            match config.format {
+                LogFormat::Text => builder.init(),
+                LogFormat::Json => builder.json().init(),
+            }
+        }
+        None => match config.format {
+            LogFormat::Text => builder.init(),
+            LogFormat::Json => builder.json().init(),
+        },
+    }
+
+    Ok(())
+}
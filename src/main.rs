@@ -1,66 +1,199 @@
 use crate::{
     backtesting::BackTesting,
+    check::run_check,
+    config::{AppConfig, ConfigWatcher},
     data::{Candles, OrderReq, Signal, TradingBot},
     db::Database,
+    notification::AlertNotifier,
+    rate_limiter::RateLimiter,
+    replay::ReplaySource,
     rest_client::BinanceClient,
-    websocket::WebSocketClient,
+    signal::MarketSignal,
+    websocket::{KlineEvent, WebSocketClient},
 };
 use anyhow::Result;
 use dotenv::dotenv;
-use futures_util::{pin_mut, StreamExt};
+use futures_util::{future::select_all, pin_mut, StreamExt};
 use rust_decimal::{prelude::FromPrimitive, Decimal};
 use std::env;
 use std::sync::Arc;
 use tokio::{
     sync::mpsc,
-    time::{interval, sleep, Duration},
+    time::{sleep, Duration},
 };
 use tracing::{error, info, warn};
 
+mod api;
 mod backtesting;
+mod check;
+mod config;
 mod data;
 mod db;
 mod engine;
+mod logging;
+mod metrics;
 mod notification;
 mod position_manager;
+mod rate_limiter;
+mod replay;
 mod rest_client;
 mod sign;
 mod signal;
 mod websocket;
 
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
-    tracing_subscriber::fmt().init();
+
+    let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.json".to_string());
+    let args: Vec<String> = env::args().collect();
+
+    if args.iter().any(|arg| arg == "--check") {
+        return run_check(&config_path).await;
+    }
+
+    let mut config = AppConfig::from_file(&config_path)?;
+    config.validate()?;
+    logging::init(&config.logging)?;
 
     info!("Starting the bot..");
 
-    let api_key = env::var("API_KEY").expect("API key not found..");
-    let secret_key = env::var("SECRET_KEY").expect("secret key not found..");
+    let decimal_ = Decimal::from_i64(10_000).unwrap();
+    let replay_path = arg_value(&args, "--replay");
+
+    if replay_path.is_some() {
+        config.paper_trading = true;
+        config.replay = true;
+    }
+
+    let config = Arc::new(config);
+
+    if let Some(csv_path) = arg_value(&args, "--csv") {
+        let historical_data = Candles::from_csv(csv_path)?;
+        info!(
+            "Loaded {} candles from {}, running backtest",
+            historical_data.len(),
+            csv_path
+        );
+
+        let mut backtester = BackTesting::new(
+            MarketSignal::from_config(&config.indicators),
+            decimal_,
+            &config,
+        );
+        backtester.mark_to_market = args.iter().any(|arg| arg == "--mtm");
+        let result = backtester.run(historical_data, config.symbols[0].clone());
+        result.print_summary();
+
+        if let Some(json_path) = arg_value(&args, "--json") {
+            result.write_json(json_path)?;
+            info!("Backtest results written to {}", json_path);
+        }
+
+        if let Some(trades_path) = arg_value(&args, "--trades") {
+            result.write_csv(trades_path)?;
+            info!("Trade log written to {}", trades_path);
+        }
+
+        if let Some(equity_path) = arg_value(&args, "--equity") {
+            result.write_equity_csv(equity_path)?;
+            info!("Equity curve written to {}", equity_path);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(import_path) = arg_value(&args, "--import") {
+        let database_url = env::var("DATABASE_URL").expect("Database url not set..");
+        let db = Database::new(&database_url).await?;
+        let candles = Candles::from_csv(import_path)?;
+        db.save_candles(&candles).await?;
+        info!("Imported {} candles from {}", candles.len(), import_path);
+
+        return Ok(());
+    }
+
+    let (api_key, secret_key) = config.credentials()?;
     let database_url = env::var("DATABASE_URL").expect("Database url not set..");
 
-    let db = Arc::new(Database::new(&database_url).await?);
+    let db = Arc::new(
+        Database::new(&database_url)
+            .await?
+            .with_mode(if config.replay {
+                "replay"
+            } else if config.paper_trading {
+                "paper"
+            } else {
+                "live"
+            }),
+    );
     let historical_data: Vec<Candles> = db.load_from_db().await?;
-    let decimal_ = Decimal::from_i64(10_000).unwrap();
 
-    let mut backtester = BackTesting::new(decimal_);
-    let result = backtester.run(historical_data, "ETHUSDT".to_string());
-    let binance_client = Arc::new(BinanceClient::new(api_key, secret_key, true));
+    let mut backtester = BackTesting::new(
+        MarketSignal::from_config(&config.indicators),
+        decimal_,
+        &config,
+    );
+    let result = backtester.run(historical_data, config.symbols[0].clone());
+    let rate_limiter = RateLimiter::new(config.requests_per_minute, config.burst_limit);
+    let binance_client = Arc::new(BinanceClient::new(
+        api_key,
+        secret_key,
+        config.testnet,
+        rate_limiter,
+    ));
 
     let (signal_tx, mut signal_rx) = mpsc::channel::<Signal>(100);
     let (order_tx, mut order_rx) = mpsc::channel::<OrderReq>(100);
 
     result.print_summary();
 
+    let (config_watcher, config_rx) =
+        ConfigWatcher::new(config_path.clone(), config.clone(), CONFIG_POLL_INTERVAL);
+
     let bot = Arc::new(TradingBot::new(
         signal_tx.clone(),
         order_tx,
         Decimal::new(1000, 0),
         binance_client.clone(),
         db.clone(),
+        config_rx,
+        AlertNotifier::from_config(&config),
     )?);
 
-    bot.initializer().await?;
+    if config.replay {
+        db.clear_mode().await?;
+    } else {
+        bot.initializer().await?;
+    }
+    tokio::spawn(config_watcher.run());
+    tokio::spawn(bot.clone().watch_config());
+
+    if let Some(port) = config.api_port {
+        let api_bot = bot.clone();
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(api_bot, port).await {
+                error!("Control API failed: {}", e);
+            }
+        });
+    }
+
+    if let Some(port) = config.metrics_port {
+        let metrics_bot = bot.clone();
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(metrics_bot, port).await {
+                error!("Metrics server failed: {}", e);
+            }
+        });
+    }
 
     info!("Trading bot is initialized!");
 
@@ -81,53 +214,62 @@ async fn main() -> Result<()> {
                 "Order received: Side: {:?}, symbol: {} @ price: {}",
                 order.side, order.symbol, order.price
             );
+
+            if order.manual {
+                info!("Manual order {} executed", order.id);
+            }
         }
     });
 
-    let symbol = "ETH/USDT";
-    let symbol_lower = symbol.to_lowercase().replace("/", "");
+    if let Some(replay_path) = replay_path {
+        let speed = arg_value(&args, "--speed").and_then(|s| s.parse::<u32>().ok());
+        let candles = if replay_path == "db" {
+            db.load_from_db().await?
+        } else {
+            Candles::from_csv(replay_path)?
+        };
 
-    info!("Connecting to the market for symbol: {}", symbol);
+        ReplaySource::new(candles, speed)
+            .run(&bot, &config.symbols[0])
+            .await?;
 
-    let bot_clone = bot.clone();
+        return Ok(());
+    }
 
-    let ws_handler = tokio::spawn(async move {
-        let mut backoff = Duration::from_secs(1);
-        let max_backoff = Duration::from_secs(30);
-        let ws = WebSocketClient::new(&symbol_lower, "1m");
-        let mut interval = interval(Duration::from_secs(15));
+    if !config.paper_trading {
+        tokio::spawn(bot.clone().run_user_stream());
+    }
 
-        loop {
-            let stream = match ws.connect().await {
-                Ok(s) => {
-                    info!("WebSocket connected!");
-                    backoff = Duration::from_secs(1);
-                    s
-                }
-                Err(e) => {
-                    tracing::error!("WebSocket connection failed: {}", e);
-                    sleep(backoff).await;
-                    backoff = std::cmp::min(backoff * 2, max_backoff);
-                    continue;
-                }
-            };
+    let mut ws_handlers = Vec::new();
 
-            interval.tick().await;
+    for symbol in config.symbols.clone() {
+        let symbol_lower = symbol.to_lowercase().replace("/", "");
+        let quote_asset = symbol.split('/').next_back().unwrap_or("USDT").to_string();
+        let timeframe = config.timeframe.clone();
+        let heartbeat = Duration::from_secs(config.heartbeat_interval.max(1));
+        let bot_clone = bot.clone();
 
-            match binance_client.account_balance().await {
-                Ok(balance) => {
-                    info!("Account balance: {}", balance);
-                }
-                Err(e) => {
-                    error!("Failed to get account balance: {}", e);
-                }
-            }
+        info!("Connecting to the market for symbol: {}", symbol);
 
+        let ws_handler = tokio::spawn(async move {
+            let ws = WebSocketClient::new(
+                &bot_clone.binance_client.stream_url,
+                &symbol_lower,
+                &timeframe,
+                heartbeat,
+            );
+            let stream = ws.connect_resilient();
             pin_mut!(stream);
 
-            while let Some(candle_result) = stream.next().await {
-                match candle_result {
-                    Ok(candle) => {
+            if let Err(e) = bot_clone.binance_client.fetch_symbol_filters(&symbol).await {
+                warn!("Failed to fetch symbol filters for {}: {}", symbol, e);
+            }
+
+            bot_clone.refresh_account_balance(&quote_asset).await;
+
+            while let Some(event) = stream.next().await {
+                match event {
+                    KlineEvent::Candle(candle) => {
                         info!(
                             "{} | open: {}, high: {}, low: {}, close: {}, volume: {}",
                             symbol,
@@ -138,23 +280,40 @@ async fn main() -> Result<()> {
                             candle.volume
                         );
 
-                        if let Err(e) = bot_clone.process_candle(candle, symbol).await {
+                        if let Err(e) = bot_clone.process_candle(candle, &symbol).await {
                             tracing::error!("Failed to process candle data: {}", e);
+                            bot_clone
+                                .notify(&format!(
+                                    "WebSocket handler for {} stopped, failed to process candle data: {}",
+                                    symbol, e
+                                ))
+                                .await;
                             return;
                         }
                     }
-                    Err(e) => {
-                        tracing::error!("WebSocket connection failed: {}", e);
-                        return;
+                    KlineEvent::Reconnected => {
+                        info!(
+                            "WebSocket reconnected for {}, refetching recent klines",
+                            symbol
+                        );
+
+                        match bot_clone
+                            .binance_client
+                            .recent_klines(&symbol, &timeframe, 100)
+                            .await
+                        {
+                            Ok(candles) => bot_clone.backfill_candles(&symbol, candles).await,
+                            Err(e) => warn!("Failed to refetch klines for {}: {}", symbol, e),
+                        }
+
+                        bot_clone.refresh_account_balance(&quote_asset).await;
                     }
                 }
             }
+        });
 
-            warn!("WebSocket stream ended, reconnecting... {:#?}", backoff);
-            sleep(backoff).await;
-            backoff = std::cmp::min(backoff * 2, max_backoff);
-        }
-    });
+        ws_handlers.push(ws_handler);
+    }
 
     info!("WebSocket running; press Ctrl+C to exit!");
 
@@ -165,11 +324,22 @@ async fn main() -> Result<()> {
         result = order_monitor => {
             error!("Order monitoring thread stopped unexpectedly: {:?}", result);
         }
-        result = ws_handler => {
+        (result, _, _) = select_all(ws_handlers) => {
             error!("WebSocket handler thread stopped unexpectedly: {:?}", result);
         }
         _ = tokio::signal::ctrl_c() => {
-            info!("Ctrl+C received!")
+            info!("Ctrl+C received!");
+
+            tokio::select! {
+                result = bot.shutdown() => {
+                    if let Err(e) = result {
+                        error!("Failed to shut down cleanly: {}", e);
+                    }
+                }
+                _ = sleep(Duration::from_secs(10)) => {
+                    warn!("Shutdown timed out, exiting anyway...");
+                }
+            }
         }
     }
 
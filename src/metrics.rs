@@ -0,0 +1,118 @@
+use crate::data::TradingBot;
+use anyhow::Result;
+use axum::{extract::State, routing::get, Router};
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+pub struct Metrics {
+    pub orders_placed: AtomicU64,
+    pub orders_rejected: AtomicU64,
+    pub ws_reconnects: AtomicU64,
+}
+
+pub static METRICS: Metrics = Metrics {
+    orders_placed: AtomicU64::new(0),
+    orders_rejected: AtomicU64::new(0),
+    ws_reconnects: AtomicU64::new(0),
+};
+
+impl Metrics {
+    pub fn record_order_placed(&self) {
+        self.orders_placed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_order_rejected(&self) {
+        self.orders_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_ws_reconnect(&self) {
+        self.ws_reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub async fn render(&self, bot: &TradingBot) -> String {
+        let open_positions = bot.position_manager.position.read().await.len();
+        let balance = *bot.account_balance.read().await;
+        let unrealized_pnl = bot.unrealized_pnl().await;
+        let realized_pnl = match bot.db.total_realized_pnl().await {
+            Ok(pnl) => pnl,
+            Err(e) => {
+                warn!("Failed to load realized PnL for metrics: {}", e);
+                Default::default()
+            }
+        };
+
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+
+        metric(
+            "sniper_open_positions",
+            "gauge",
+            "Number of open positions",
+            open_positions.to_string(),
+        );
+        metric(
+            "sniper_realized_pnl_total",
+            "gauge",
+            "Total realized PnL across closed trades",
+            realized_pnl.to_string(),
+        );
+        metric(
+            "sniper_unrealized_pnl",
+            "gauge",
+            "Unrealized PnL of open positions at the latest close",
+            unrealized_pnl.to_string(),
+        );
+        metric(
+            "sniper_account_balance",
+            "gauge",
+            "Current quote asset account balance",
+            balance.to_string(),
+        );
+        metric(
+            "sniper_orders_placed_total",
+            "counter",
+            "Orders accepted by the exchange",
+            self.orders_placed.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "sniper_orders_rejected_total",
+            "counter",
+            "Orders rejected by the exchange or failed to submit",
+            self.orders_rejected.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "sniper_ws_reconnects_total",
+            "counter",
+            "Market data WebSocket reconnects",
+            self.ws_reconnects.load(Ordering::Relaxed).to_string(),
+        );
+
+        out
+    }
+}
+
+async fn metrics_handler(State(bot): State<Arc<TradingBot>>) -> String {
+    METRICS.render(&bot).await
+}
+
+pub async fn serve(bot: Arc<TradingBot>, port: u16) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(bot);
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("Metrics server listening on port {}", port);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
@@ -1 +1,185 @@
+use crate::config::{AppConfig, NotifierKind};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::json;
+use std::{env, sync::Arc};
+use tokio::{
+    sync::Mutex,
+    time::{sleep_until, Duration, Instant},
+};
+use tracing::warn;
 
+const ALERT_WINDOW: Duration = Duration::from_secs(60);
+
+pub trait Notifier {
+    async fn notify(&self, msg: &str) -> Result<()>;
+}
+
+pub struct TelegramNotifier {
+    pub client: Client,
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: String, chat_id: String) -> Self {
+        Self {
+            client: Client::new(),
+            bot_token,
+            chat_id,
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let bot_token = env::var("TELEGRAM_BOT_TOKEN").ok()?;
+        let chat_id = env::var("TELEGRAM_CHAT_ID").ok()?;
+        Some(Self::new(bot_token, chat_id))
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, msg: &str) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        let response = self
+            .client
+            .post(url)
+            .json(&json!({ "chat_id": self.chat_id, "text": msg }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while sending Telegram notification: {:?}",
+                response.text().await
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub struct AlertBatch {
+    pub window_start: Instant,
+    pub sent: u32,
+    pub pending: Vec<String>,
+}
+
+pub struct DiscordWebhookNotifier {
+    pub client: Client,
+    pub webhook_url: String,
+    pub max_per_minute: Option<u32>,
+    pub batch: Arc<Mutex<AlertBatch>>,
+}
+
+impl DiscordWebhookNotifier {
+    pub fn new(webhook_url: String, max_per_minute: Option<u32>) -> Self {
+        Self {
+            client: Client::new(),
+            webhook_url,
+            max_per_minute,
+            batch: Arc::new(Mutex::new(AlertBatch {
+                window_start: Instant::now(),
+                sent: 0,
+                pending: Vec::new(),
+            })),
+        }
+    }
+
+    async fn post(client: &Client, webhook_url: &str, content: &str) -> Result<()> {
+        let response = client
+            .post(webhook_url)
+            .json(&json!({ "content": content }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while sending Discord notification: {:?}",
+                response.text().await
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Notifier for DiscordWebhookNotifier {
+    async fn notify(&self, msg: &str) -> Result<()> {
+        let Some(max_per_minute) = self.max_per_minute else {
+            return Self::post(&self.client, &self.webhook_url, msg).await;
+        };
+
+        let mut batch = self.batch.lock().await;
+        let now = Instant::now();
+
+        if now.duration_since(batch.window_start) >= ALERT_WINDOW {
+            batch.window_start = now;
+            batch.sent = 0;
+        }
+
+        if batch.sent < max_per_minute && batch.pending.is_empty() {
+            batch.sent += 1;
+            drop(batch);
+            return Self::post(&self.client, &self.webhook_url, msg).await;
+        }
+
+        batch.pending.push(msg.to_string());
+
+        if batch.pending.len() == 1 {
+            let flush_at = batch.window_start + ALERT_WINDOW;
+            let client = self.client.clone();
+            let webhook_url = self.webhook_url.clone();
+            let pending = self.batch.clone();
+
+            tokio::spawn(async move {
+                sleep_until(flush_at).await;
+
+                let content = {
+                    let mut batch = pending.lock().await;
+                    batch.window_start = Instant::now();
+                    batch.sent = 1;
+                    batch.pending.drain(..).collect::<Vec<_>>().join("\n")
+                };
+
+                if let Err(e) = Self::post(&client, &webhook_url, &content).await {
+                    warn!("Failed to send batched Discord notification: {}", e);
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+pub enum AlertNotifier {
+    Telegram(TelegramNotifier),
+    Discord(DiscordWebhookNotifier),
+}
+
+impl AlertNotifier {
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        match config.notifier {
+            NotifierKind::Telegram => TelegramNotifier::from_env().map(Self::Telegram),
+            NotifierKind::Discord => config
+                .webhook_url
+                .clone()
+                .or_else(|| env::var("WEBHOOK_URL").ok())
+                .map(|url| {
+                    Self::Discord(DiscordWebhookNotifier::new(
+                        url,
+                        config.max_alerts_per_minute,
+                    ))
+                }),
+            NotifierKind::None => None,
+        }
+    }
+}
+
+impl Notifier for AlertNotifier {
+    async fn notify(&self, msg: &str) -> Result<()> {
+        match self {
+            Self::Telegram(notifier) => notifier.notify(msg).await,
+            Self::Discord(notifier) => notifier.notify(msg).await,
+        }
+    }
+}
@@ -1,28 +1,251 @@
 use crate::{
-    data::{Position, PositionSide},
+    config::AppConfig,
+    data::{OrderType, Position, PositionSide, SymbolFilters},
     db::Database,
+    rest_client::BinanceClient,
 };
 use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, Utc};
 use rust_decimal::Decimal;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub fn round_to_lot(size: Decimal, price: Decimal, filters: &SymbolFilters) -> Decimal {
+    let rounded = if filters.step_size > Decimal::ZERO {
+        (size / filters.step_size).floor() * filters.step_size
+    } else {
+        size
+    };
+
+    if rounded <= Decimal::ZERO
+        || rounded < filters.min_qty
+        || rounded * price < filters.min_notional
+    {
+        return Decimal::ZERO;
+    }
+
+    rounded.normalize()
+}
+
+pub fn reconcile_longs(
+    positions: &[Position],
+    symbol: &str,
+    holding: Decimal,
+) -> (Vec<String>, Decimal) {
+    let mut longs: Vec<&Position> = positions
+        .iter()
+        .filter(|p| p.symbol == symbol && matches!(p.position_side, PositionSide::Long))
+        .collect();
+    let tracked: Decimal = longs.iter().map(|p| p.size).sum();
+
+    if holding >= tracked {
+        return (Vec::new(), holding - tracked);
+    }
+
+    longs.sort_by_key(|p| std::cmp::Reverse(p.opened_at));
+    let mut remaining = tracked;
+    let mut to_close = Vec::new();
+
+    for position in longs {
+        if remaining <= holding {
+            break;
+        }
+
+        to_close.push(position.id.clone());
+        remaining -= position.size;
+    }
+
+    (to_close, Decimal::ZERO)
+}
+
+pub fn round_to_tick(price: Decimal, tick: Decimal) -> Decimal {
+    if tick <= Decimal::ZERO {
+        return price;
+    }
+
+    ((price / tick).floor() * tick).normalize()
+}
+
+pub struct DailyLimits {
+    pub max_daily_loss: Option<Decimal>,
+    pub max_daily_trades: Option<u32>,
+    pub realized_pnl: Decimal,
+    pub trade_count: u32,
+    pub day: NaiveDate,
+}
+
+impl DailyLimits {
+    pub fn new(max_daily_loss: Option<Decimal>, max_daily_trades: Option<u32>) -> Self {
+        Self {
+            max_daily_loss,
+            max_daily_trades,
+            realized_pnl: Decimal::ZERO,
+            trade_count: 0,
+            day: Utc::now().date_naive(),
+        }
+    }
+
+    fn roll_over(&mut self) {
+        let today = Utc::now().date_naive();
+
+        if today != self.day {
+            info!(
+                "New trading day {}, resetting daily limits (pnl: {}, trades: {})",
+                today, self.realized_pnl, self.trade_count
+            );
+            self.day = today;
+            self.realized_pnl = Decimal::ZERO;
+            self.trade_count = 0;
+        }
+    }
+
+    pub fn record_trade(&mut self) {
+        self.roll_over();
+        self.trade_count += 1;
+    }
+
+    pub fn record_pnl(&mut self, pnl: Decimal) {
+        self.roll_over();
+        self.realized_pnl += pnl;
+    }
+
+    pub fn can_trade(&self) -> bool {
+        if Utc::now().date_naive() != self.day {
+            return true;
+        }
+
+        if let Some(max_loss) = self.max_daily_loss {
+            if self.realized_pnl <= -max_loss {
+                return false;
+            }
+        }
+
+        if let Some(max_trades) = self.max_daily_trades {
+            if self.trade_count >= max_trades {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+pub struct CircuitBreaker {
+    pub max_consecutive_losses: Option<u32>,
+    pub cooldown_secs: i64,
+    pub consecutive_losses: u32,
+    pub tripped_until: Option<i64>,
+}
+
+impl CircuitBreaker {
+    pub fn new(max_consecutive_losses: Option<u32>, cooldown_secs: i64) -> Self {
+        Self {
+            max_consecutive_losses,
+            cooldown_secs,
+            consecutive_losses: 0,
+            tripped_until: None,
+        }
+    }
+
+    pub fn record_close(&mut self, pnl: Decimal) {
+        if pnl >= Decimal::ZERO {
+            if self.consecutive_losses > 0 {
+                info!(
+                    "Winning close, resetting consecutive losses from {}",
+                    self.consecutive_losses
+                );
+            }
+            self.consecutive_losses = 0;
+            return;
+        }
+
+        self.consecutive_losses += 1;
+
+        if let Some(max_losses) = self.max_consecutive_losses {
+            if self.consecutive_losses >= max_losses {
+                let until = Utc::now().timestamp() + self.cooldown_secs;
+                warn!(
+                    "Circuit breaker tripped after {} consecutive losses, pausing entries for {}s",
+                    self.consecutive_losses, self.cooldown_secs
+                );
+                self.tripped_until = Some(until);
+                self.consecutive_losses = 0;
+            }
+        }
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped_until
+            .is_some_and(|until| Utc::now().timestamp() < until)
+    }
+}
 
 pub struct PositionManager {
     pub position: Arc<RwLock<Vec<Position>>>,
-    pub risk_per_trade: Decimal,
+    pub risk_per_trade: RwLock<Decimal>,
+    pub max_position_size: RwLock<Option<Decimal>>,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    pub daily_limits: RwLock<DailyLimits>,
+    pub circuit_breaker: RwLock<CircuitBreaker>,
+    pub position_limits: RwLock<(usize, usize)>,
+    pub pending_entries: RwLock<HashMap<String, usize>>,
+    pub closed_pnl: RwLock<Decimal>,
     pub db: Arc<Database>,
 }
 
 impl PositionManager {
-    pub fn new(risk_per_trade: Decimal, db: Arc<Database>) -> Self {
+    pub fn new(config: &AppConfig, db: Arc<Database>) -> Self {
         Self {
             position: Arc::new(RwLock::new(Vec::new())),
-            risk_per_trade,
+            risk_per_trade: RwLock::new(config.risk_per_trade / Decimal::new(100, 0)),
+            max_position_size: RwLock::new(config.max_position_size),
+            maker_fee: config.maker_fee_pct / Decimal::new(100, 0),
+            taker_fee: config.taker_fee_pct / Decimal::new(100, 0),
+            daily_limits: RwLock::new(DailyLimits::new(
+                config.max_daily_loss,
+                config.max_daily_trades,
+            )),
+            circuit_breaker: RwLock::new(CircuitBreaker::new(
+                config.max_consecutive_losses,
+                config.circuit_breaker_cooldown_secs as i64,
+            )),
+            position_limits: RwLock::new((config.max_positions, config.max_positions_per_symbol)),
+            pending_entries: RwLock::new(HashMap::new()),
+            closed_pnl: RwLock::new(Decimal::ZERO),
             db,
         }
     }
 
+    pub async fn update_risk(&self, config: &AppConfig) {
+        *self.risk_per_trade.write().await = config.risk_per_trade / Decimal::new(100, 0);
+        *self.max_position_size.write().await = config.max_position_size;
+        let mut daily_limits = self.daily_limits.write().await;
+        daily_limits.max_daily_loss = config.max_daily_loss;
+        daily_limits.max_daily_trades = config.max_daily_trades;
+        let mut circuit_breaker = self.circuit_breaker.write().await;
+        circuit_breaker.max_consecutive_losses = config.max_consecutive_losses;
+        circuit_breaker.cooldown_secs = config.circuit_breaker_cooldown_secs as i64;
+        *self.position_limits.write().await =
+            (config.max_positions, config.max_positions_per_symbol);
+    }
+
+    pub fn fee(&self, order_type: &OrderType, price: Decimal, size: Decimal) -> Decimal {
+        let rate = match order_type {
+            OrderType::Limit => self.maker_fee,
+            OrderType::Market => self.taker_fee,
+        };
+
+        price * size * rate
+    }
+
+    pub async fn can_trade(&self) -> bool {
+        self.daily_limits.read().await.can_trade()
+            && !self.circuit_breaker.read().await.is_tripped()
+    }
+
     pub async fn load_open_orders(&self) -> Result<()> {
         let positions = self.db.get_open_orders().await?;
         let count = positions.len();
@@ -31,6 +254,98 @@ impl PositionManager {
 
         info!("Loaded open positions into the database: {}", count);
 
+        let start_of_day = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc())
+            .unwrap_or_else(Utc::now);
+        let closed_today = self.db.get_closed_trades(start_of_day).await?;
+        let pnl_today: Decimal = closed_today.iter().map(|t| t.pnl).sum();
+        self.daily_limits.write().await.record_pnl(pnl_today);
+
+        info!(
+            "Closed trades today: {}, realized pnl today: {}, realized pnl overall: {}",
+            closed_today.len(),
+            pnl_today,
+            self.db.total_realized_pnl().await?
+        );
+
+        Ok(())
+    }
+
+    pub async fn reconcile(&self, client: &BinanceClient, config: &AppConfig) -> Result<()> {
+        let positions = self.position.read().await.clone();
+        let mut symbols = config.symbols.clone();
+
+        for position in &positions {
+            if !symbols.contains(&position.symbol) {
+                symbols.push(position.symbol.clone());
+            }
+
+            if let PositionSide::Short = position.position_side {
+                warn!(
+                    "Short position {} on {} cannot be checked against spot balances, keeping it as tracked",
+                    position.id, position.symbol
+                );
+            }
+        }
+
+        for symbol in symbols {
+            let base_asset = symbol.split('/').next().unwrap_or(&symbol);
+            let holding = client.asset_holding(base_asset).await?;
+            let (to_close, untracked) = reconcile_longs(&positions, &symbol, holding);
+
+            if to_close.is_empty() && untracked.is_zero() {
+                continue;
+            }
+
+            let price = client
+                .recent_klines(&symbol, "1m", 1)
+                .await?
+                .last()
+                .map(|c| c.close)
+                .ok_or_else(|| anyhow!("No recent price for: {}", symbol))?;
+
+            for position_id in to_close {
+                warn!(
+                    "Position {} on {} no longer held on the exchange, marking it closed",
+                    position_id, symbol
+                );
+                self.close_positions(&position_id, price, &OrderType::Market)
+                    .await?;
+            }
+
+            let filters = client.fetch_symbol_filters(&symbol).await?;
+            let size = round_to_lot(untracked, price, &filters);
+
+            if size.is_zero() {
+                continue;
+            }
+
+            let position = Position {
+                id: format!("r{}", Uuid::new_v4().simple()),
+                symbol: symbol.clone(),
+                position_side: PositionSide::Long,
+                entry_price: price,
+                size,
+                stop_loss: price - price * config.stop_loss_percent / Decimal::new(100, 0),
+                take_profit: price + price * config.take_profit_percent / Decimal::new(100, 0),
+                opened_at: Utc::now().timestamp(),
+                realized_pnl: Decimal::ZERO,
+                trailing_pct: config
+                    .trailing_stop_percent
+                    .map(|pct| pct / Decimal::new(100, 0)),
+                adds: 0,
+                last_add_price: None,
+            };
+
+            warn!(
+                "Exchange holds {} {} not tracked by the bot, importing it as position {} @ {}",
+                size, base_asset, position.id, price
+            );
+            self.open_position(position, true).await?;
+        }
+
         Ok(())
     }
 
@@ -45,40 +360,171 @@ impl PositionManager {
         }
     }
 
+    #[allow(dead_code)]
     pub async fn has_positions(&self) -> bool {
         let positions = self.position.read().await;
         !positions.is_empty()
     }
 
+    pub async fn reserve_capacity(&self, symbol: &str) -> Result<()> {
+        let mut pending = self.pending_entries.write().await;
+        let (max_total, max_per_symbol) = *self.position_limits.read().await;
+        let (total, for_symbol) = {
+            let positions = self.position.read().await;
+            (
+                positions.len() + pending.values().sum::<usize>(),
+                positions.iter().filter(|p| p.symbol == symbol).count()
+                    + pending.get(symbol).copied().unwrap_or(0),
+            )
+        };
+
+        if total >= max_total {
+            return Err(anyhow!(
+                "Max total positions of {} reached, rejecting position on {}",
+                max_total,
+                symbol
+            ));
+        }
+
+        if for_symbol >= max_per_symbol {
+            return Err(anyhow!(
+                "Max positions per symbol of {} reached for {}",
+                max_per_symbol,
+                symbol
+            ));
+        }
+
+        *pending.entry(symbol.to_string()).or_insert(0) += 1;
+        Ok(())
+    }
+
+    pub async fn release_capacity(&self, symbol: &str) {
+        let mut pending = self.pending_entries.write().await;
+
+        if let Some(count) = pending.get_mut(symbol) {
+            *count = count.saturating_sub(1);
+
+            if *count == 0 {
+                pending.remove(symbol);
+            }
+        }
+    }
+
+    pub async fn total_exposure(&self, prices: &HashMap<String, Decimal>) -> Decimal {
+        let positions = self.position.read().await;
+
+        positions
+            .iter()
+            .map(|p| {
+                let price = prices.get(&p.symbol).copied().unwrap_or(p.entry_price);
+                p.size * price
+            })
+            .sum()
+    }
+
+    pub async fn unrealized_pnl(&self, prices: &HashMap<String, Decimal>) -> Decimal {
+        let positions = self.position.read().await;
+
+        positions
+            .iter()
+            .map(|p| {
+                let price = prices.get(&p.symbol).copied().unwrap_or(p.entry_price);
+                match p.position_side {
+                    PositionSide::Long => (price - p.entry_price) * p.size,
+                    PositionSide::Short => (p.entry_price - price) * p.size,
+                }
+            })
+            .sum()
+    }
+
+    pub async fn open_pnl(&self, prices: &HashMap<String, Decimal>) -> Decimal {
+        let realized: Decimal = self
+            .position
+            .read()
+            .await
+            .iter()
+            .map(|p| p.realized_pnl)
+            .sum();
+
+        realized + self.unrealized_pnl(prices).await
+    }
+
+    pub async fn exceeds_max_exposure(
+        &self,
+        prices: &HashMap<String, Decimal>,
+        max_exposure: Decimal,
+    ) -> bool {
+        self.total_exposure(prices).await >= max_exposure
+    }
+
     pub async fn open_position(&self, position: Position, manual: bool) -> Result<()> {
         if position.entry_price == Decimal::ZERO || position.size == Decimal::ZERO {
             info!("Attempt to open position with size zero, rejected...");
             return Ok(());
         }
 
-        if !self.has_positions().await {
-            self.db.save_order(&position, manual).await?;
-            let mut positions = self.position.write().await;
-            positions.push(position.clone());
-        }
+        self.db.save_order(&position, manual).await?;
+        let mut positions = self.position.write().await;
+        positions.push(position.clone());
+        self.daily_limits.write().await.record_trade();
 
         info!("New position opened!");
         Ok(())
     }
 
-    pub async fn close_positions(&self, position_id: &str, exit_price: Decimal) -> Result<()> {
+    pub async fn add_to_position(
+        &self,
+        position_id: &str,
+        added: Decimal,
+        price: Decimal,
+        order_type: &OrderType,
+    ) -> Result<()> {
+        let mut positions = self.position.write().await;
+        let position = positions
+            .iter_mut()
+            .find(|p| p.id == position_id)
+            .ok_or_else(|| anyhow!("No open position found for id: {}", position_id))?;
+
+        let size = position.size + added;
+        position.entry_price = (position.entry_price * position.size + price * added) / size;
+        position.size = size;
+        position.adds += 1;
+        position.last_add_price = Some(price);
+        position.realized_pnl -= self.fee(order_type, price, added);
+
+        self.db.update_pyramid(position).await?;
+
+        info!(
+            "Added {} to position {} @ {}, size: {}, average entry: {}, adds: {}",
+            added, position_id, price, position.size, position.entry_price, position.adds
+        );
+
+        Ok(())
+    }
+
+    pub async fn close_positions(
+        &self,
+        position_id: &str,
+        exit_price: Decimal,
+        order_type: &OrderType,
+    ) -> Result<()> {
         let mut positions = self.position.write().await;
 
-        if !self.has_positions().await {
+        if positions.is_empty() {
             return Err(anyhow!("No open positions found to be closed!"));
         }
 
         if let Some(pos) = positions.iter().find(|p| p.id == position_id) {
-            let pnl = match pos.position_side {
-                PositionSide::Long => (exit_price - pos.entry_price) * pos.size,
-                PositionSide::Short => (pos.entry_price - exit_price) * pos.size,
-            };
+            let pnl = pos.realized_pnl
+                + match pos.position_side {
+                    PositionSide::Long => (exit_price - pos.entry_price) * pos.size,
+                    PositionSide::Short => (pos.entry_price - exit_price) * pos.size,
+                }
+                - self.fee(order_type, exit_price, pos.size);
             self.db.close_order(position_id, exit_price, pnl).await?;
+            self.daily_limits.write().await.record_pnl(pnl);
+            self.circuit_breaker.write().await.record_close(pnl);
+            *self.closed_pnl.write().await += pnl;
             info!(
                 "Closed position for id: {} at price: {} at pnl: {}",
                 position_id, exit_price, pnl
@@ -90,19 +536,101 @@ impl PositionManager {
         Ok(())
     }
 
+    pub fn apply_fill(
+        &self,
+        pos: &mut Position,
+        filled: Decimal,
+        price: Decimal,
+        order_type: &OrderType,
+    ) {
+        let filled = filled.min(pos.size);
+        let pnl = match pos.position_side {
+            PositionSide::Long => (price - pos.entry_price) * filled,
+            PositionSide::Short => (pos.entry_price - price) * filled,
+        } - self.fee(order_type, price, filled);
+
+        pos.size -= filled;
+        pos.realized_pnl += pnl;
+    }
+
+    pub async fn reduce_position(
+        &self,
+        position_id: &str,
+        filled: Decimal,
+        price: Decimal,
+        order_type: &OrderType,
+    ) -> Result<()> {
+        if filled <= Decimal::ZERO {
+            return Err(anyhow!(
+                "Invalid fill size {} for position: {}",
+                filled,
+                position_id
+            ));
+        }
+
+        let (remaining, realized_pnl) = {
+            let mut positions = self.position.write().await;
+            let pos = positions
+                .iter_mut()
+                .find(|p| p.id == position_id)
+                .ok_or_else(|| anyhow!("No open position found for id: {}", position_id))?;
+
+            self.apply_fill(pos, filled, price, order_type);
+            (pos.size, pos.realized_pnl)
+        };
+
+        if remaining.is_zero() {
+            return self.close_positions(position_id, price, order_type).await;
+        }
+
+        self.db
+            .update_order_size(position_id, remaining, realized_pnl)
+            .await?;
+
+        info!(
+            "Reduced position for id: {} at price: {}, remaining size: {}, realized pnl: {}",
+            position_id, price, remaining, realized_pnl
+        );
+
+        Ok(())
+    }
+
     pub async fn check_positions(
         &self,
         current_price: Decimal,
         symbol: &str,
     ) -> Vec<(String, Decimal, PositionSide)> {
-        let positions = self.position.read().await;
+        let mut positions = self.position.write().await;
         let mut to_close = Vec::new();
+        let mut trailed_stops = Vec::new();
 
-        for position in positions.iter() {
+        for position in positions.iter_mut() {
             if position.symbol != symbol {
                 continue;
             }
 
+            if let Some(trailing_pct) = position.trailing_pct {
+                let trailed_stop = match position.position_side {
+                    PositionSide::Long => {
+                        let stop = current_price * (Decimal::ONE - trailing_pct);
+                        (stop > position.stop_loss).then_some(stop)
+                    }
+                    PositionSide::Short => {
+                        let stop = current_price * (Decimal::ONE + trailing_pct);
+                        (stop < position.stop_loss).then_some(stop)
+                    }
+                };
+
+                if let Some(stop) = trailed_stop {
+                    info!(
+                        "Trailing stop moved for id: {} from {} to {}",
+                        position.id, position.stop_loss, stop
+                    );
+                    position.stop_loss = stop;
+                    trailed_stops.push((position.id.clone(), stop));
+                }
+            }
+
             match position.position_side {
                 PositionSide::Long => {
                     if current_price <= position.stop_loss {
@@ -141,6 +669,17 @@ impl PositionManager {
             }
         }
 
+        drop(positions);
+
+        for (position_id, stop_loss) in trailed_stops {
+            if let Err(e) = self.db.update_stop_loss(&position_id, stop_loss).await {
+                warn!(
+                    "Failed to persist trailing stop for id: {}: {}",
+                    position_id, e
+                );
+            }
+        }
+
         to_close
     }
 
@@ -150,13 +689,378 @@ impl PositionManager {
         entry_price: Decimal,
         stop_loss: Decimal,
     ) -> Decimal {
-        let risk_amount = account_balance * self.risk_per_trade;
+        if account_balance <= Decimal::ZERO
+            || entry_price <= Decimal::ZERO
+            || stop_loss <= Decimal::ZERO
+        {
+            return Decimal::ZERO;
+        }
+
+        let risk_amount = account_balance * *self.risk_per_trade.read().await;
         let risk_per_unit = (entry_price - stop_loss).abs();
 
         if risk_per_unit == Decimal::ZERO {
             return Decimal::ZERO;
         }
 
-        risk_amount / risk_per_unit
+        let size = risk_amount
+            .checked_div(risk_per_unit)
+            .unwrap_or(Decimal::MAX)
+            .min(account_balance / entry_price);
+
+        match *self.max_position_size.read().await {
+            Some(max_size) => size.min(max_size),
+            None => size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn filters() -> SymbolFilters {
+        SymbolFilters {
+            step_size: dec("0.001"),
+            min_qty: dec("0.01"),
+            tick_size: dec("0.01"),
+            min_notional: dec("10"),
+        }
+    }
+
+    fn manager(overrides: serde_json::Value) -> PositionManager {
+        let mut config = serde_json::json!({
+            "symbols": ["BTCUSDT"],
+            "timeframe": "1m",
+            "size": "1",
+            "risk_per_trade": "1",
+            "max_positions": 5,
+            "min_confidence": "0.5",
+            "stop_loss_percent": "2",
+            "take_profit_percent": "4",
+            "maker_fee_pct": "0.1",
+            "taker_fee_pct": "0.2"
+        });
+        config
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        let config: AppConfig = serde_json::from_value(config).unwrap();
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_millis(50))
+            .connect_lazy("postgres://localhost:1/sniper")
+            .unwrap();
+
+        PositionManager::new(&config, Arc::new(Database { pool, mode: "test" }))
+    }
+
+    fn long(entry_price: &str, size: &str, stop_loss: &str, take_profit: &str) -> Position {
+        Position {
+            id: "pos-1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            position_side: PositionSide::Long,
+            entry_price: dec(entry_price),
+            size: dec(size),
+            stop_loss: dec(stop_loss),
+            take_profit: dec(take_profit),
+            opened_at: 0,
+            realized_pnl: Decimal::ZERO,
+            trailing_pct: None,
+            adds: 0,
+            last_add_price: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn two_partial_fills_close_the_position() {
+        let manager = manager(serde_json::json!({}));
+        let mut pos = long("100", "1", "90", "130");
+
+        manager.apply_fill(&mut pos, dec("0.4"), dec("110"), &OrderType::Limit);
+        assert_eq!(pos.size, dec("0.6"));
+        assert_eq!(pos.realized_pnl, dec("3.956"));
+
+        manager.apply_fill(&mut pos, dec("0.6"), dec("120"), &OrderType::Limit);
+        assert!(pos.size.is_zero());
+        assert_eq!(pos.realized_pnl, dec("15.884"));
+    }
+
+    #[tokio::test]
+    async fn fill_larger_than_position_is_capped() {
+        let manager = manager(serde_json::json!({}));
+        let mut pos = long("100", "0.5", "90", "130");
+
+        manager.apply_fill(&mut pos, dec("2"), dec("110"), &OrderType::Market);
+        assert!(pos.size.is_zero());
+        assert_eq!(pos.realized_pnl, dec("4.89"));
+    }
+
+    #[tokio::test]
+    async fn trailing_stop_closes_above_the_original_stop() {
+        let manager = manager(serde_json::json!({}));
+        let mut pos = long("100", "1", "95", "200");
+        pos.trailing_pct = Some(dec("0.05"));
+        manager.position.write().await.push(pos);
+
+        assert!(manager
+            .check_positions(dec("110"), "BTCUSDT")
+            .await
+            .is_empty());
+        assert!(manager
+            .check_positions(dec("120"), "BTCUSDT")
+            .await
+            .is_empty());
+        assert_eq!(manager.position.read().await[0].stop_loss, dec("114"));
+
+        assert!(manager
+            .check_positions(dec("116"), "BTCUSDT")
+            .await
+            .is_empty());
+        assert_eq!(manager.position.read().await[0].stop_loss, dec("114"));
+
+        let to_close = manager.check_positions(dec("113"), "BTCUSDT").await;
+        assert_eq!(to_close.len(), 1);
+        assert_eq!(to_close[0].0, "pos-1");
+        assert!(to_close[0].1 > dec("95"));
+    }
+
+    #[tokio::test]
+    async fn position_size_risks_a_fraction_of_the_balance() {
+        let manager = manager(serde_json::json!({}));
+
+        let size = manager
+            .calculate_position_size(dec("10000"), dec("100"), dec("95"))
+            .await;
+        assert_eq!(size, dec("20"));
+    }
+
+    #[tokio::test]
+    async fn tight_stop_is_capped_by_the_balance() {
+        let capped = manager(serde_json::json!({ "max_position_size": "5" }));
+        let manager = manager(serde_json::json!({}));
+        let entry = Decimal::ONE;
+        let stop = entry - Decimal::new(1, 27);
+
+        let size = manager
+            .calculate_position_size(dec("10000"), entry, stop)
+            .await;
+        assert_eq!(size, dec("10000"));
+
+        let size = capped
+            .calculate_position_size(dec("10000"), entry, stop)
+            .await;
+        assert_eq!(size, dec("5"));
+    }
+
+    #[tokio::test]
+    async fn zero_or_flat_stop_sizes_to_zero() {
+        let manager = manager(serde_json::json!({}));
+
+        let size = manager
+            .calculate_position_size(dec("10000"), dec("100"), Decimal::ZERO)
+            .await;
+        assert!(size.is_zero());
+
+        let size = manager
+            .calculate_position_size(dec("10000"), dec("100"), dec("100"))
+            .await;
+        assert!(size.is_zero());
+    }
+
+    fn held(id: &str, side: PositionSide, size: &str, opened_at: i64) -> Position {
+        Position {
+            id: id.to_string(),
+            position_side: side,
+            opened_at,
+            ..long("100", size, "90", "130")
+        }
+    }
+
+    #[test]
+    fn reconcile_closes_newest_longs_missing_on_the_exchange() {
+        let positions = vec![
+            held("old", PositionSide::Long, "1", 1),
+            held("mid", PositionSide::Long, "2", 2),
+            held("new", PositionSide::Long, "3", 3),
+        ];
+
+        let (to_close, untracked) = reconcile_longs(&positions, "BTCUSDT", dec("3"));
+        assert_eq!(to_close, vec!["new".to_string()]);
+        assert!(untracked.is_zero());
+
+        let (to_close, _) = reconcile_longs(&positions, "BTCUSDT", dec("0.5"));
+        assert_eq!(to_close, vec!["new", "mid", "old"]);
+    }
+
+    #[test]
+    fn reconcile_imports_untracked_holdings() {
+        let positions = vec![
+            held("long", PositionSide::Long, "1", 1),
+            held("short", PositionSide::Short, "4", 2),
+        ];
+
+        let (to_close, untracked) = reconcile_longs(&positions, "BTCUSDT", dec("1.5"));
+        assert!(to_close.is_empty());
+        assert_eq!(untracked, dec("0.5"));
+
+        let (to_close, untracked) = reconcile_longs(&positions, "ETHUSDT", dec("2"));
+        assert!(to_close.is_empty());
+        assert_eq!(untracked, dec("2"));
+    }
+
+    #[test]
+    fn reconcile_leaves_matching_holdings_alone() {
+        let positions = vec![held("long", PositionSide::Long, "1", 1)];
+
+        let (to_close, untracked) = reconcile_longs(&positions, "BTCUSDT", dec("1"));
+        assert!(to_close.is_empty());
+        assert!(untracked.is_zero());
+    }
+
+    #[test]
+    fn round_to_lot_floors_to_step_size() {
+        assert_eq!(
+            round_to_lot(dec("0.123456"), dec("2000"), &filters()),
+            dec("0.123")
+        );
+        assert_eq!(
+            round_to_lot(dec("1.9999"), dec("2000"), &filters()),
+            dec("1.999")
+        );
+    }
+
+    #[test]
+    fn round_to_lot_rejects_below_min_qty() {
+        assert_eq!(
+            round_to_lot(dec("0.0099"), dec("5000"), &filters()),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn round_to_lot_rejects_below_min_notional() {
+        assert_eq!(
+            round_to_lot(dec("0.02"), dec("100"), &filters()),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn round_to_lot_without_step_keeps_size() {
+        let filters = SymbolFilters {
+            step_size: Decimal::ZERO,
+            min_qty: Decimal::ZERO,
+            tick_size: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+        };
+
+        assert_eq!(
+            round_to_lot(dec("0.123456"), dec("1"), &filters),
+            dec("0.123456")
+        );
+    }
+
+    #[test]
+    fn round_to_tick_floors_to_tick_size() {
+        assert_eq!(
+            round_to_tick(dec("75000.037"), dec("0.01")),
+            dec("75000.03")
+        );
+        assert_eq!(round_to_tick(dec("2500.5"), dec("0.5")), dec("2500.5"));
+        assert_eq!(round_to_tick(dec("2500.49"), dec("0.5")), dec("2500"));
+    }
+
+    #[test]
+    fn round_to_tick_without_tick_keeps_price() {
+        assert_eq!(
+            round_to_tick(dec("75000.037"), Decimal::ZERO),
+            dec("75000.037")
+        );
+    }
+
+    #[test]
+    fn daily_limits_block_after_max_trades() {
+        let mut limits = DailyLimits::new(None, Some(2));
+        limits.record_trade();
+        assert!(limits.can_trade());
+
+        limits.record_trade();
+        assert!(!limits.can_trade());
+    }
+
+    #[test]
+    fn daily_limits_block_after_max_loss() {
+        let mut limits = DailyLimits::new(Some(dec("100")), None);
+        limits.record_pnl(dec("-60"));
+        assert!(limits.can_trade());
+
+        limits.record_pnl(dec("-40"));
+        assert!(!limits.can_trade());
+    }
+
+    #[test]
+    fn daily_limits_roll_over_on_a_new_day() {
+        let mut limits = DailyLimits::new(Some(dec("100")), Some(1));
+        limits.record_trade();
+        limits.record_pnl(dec("-150"));
+        assert!(!limits.can_trade());
+
+        limits.day -= Duration::days(1);
+        assert!(limits.can_trade());
+
+        limits.record_trade();
+        assert_eq!(limits.day, Utc::now().date_naive());
+        assert_eq!(limits.trade_count, 1);
+        assert_eq!(limits.realized_pnl, Decimal::ZERO);
+    }
+
+    #[test]
+    fn circuit_breaker_trips_after_consecutive_losses() {
+        let mut breaker = CircuitBreaker::new(Some(3), 3600);
+        breaker.record_close(dec("-1"));
+        breaker.record_close(dec("-1"));
+        assert!(!breaker.is_tripped());
+
+        breaker.record_close(dec("-1"));
+        assert!(breaker.is_tripped());
+        assert_eq!(breaker.consecutive_losses, 0);
+    }
+
+    #[test]
+    fn circuit_breaker_resets_on_a_win() {
+        let mut breaker = CircuitBreaker::new(Some(3), 3600);
+        breaker.record_close(dec("-1"));
+        breaker.record_close(dec("-1"));
+        breaker.record_close(dec("5"));
+        breaker.record_close(dec("-1"));
+
+        assert!(!breaker.is_tripped());
+        assert_eq!(breaker.consecutive_losses, 1);
+    }
+
+    #[test]
+    fn circuit_breaker_expires_after_cooldown() {
+        let mut breaker = CircuitBreaker::new(Some(1), 0);
+        breaker.record_close(dec("-1"));
+
+        assert!(breaker.tripped_until.is_some());
+        assert!(!breaker.is_tripped());
+    }
+
+    #[test]
+    fn circuit_breaker_disabled_without_limit() {
+        let mut breaker = CircuitBreaker::new(None, 3600);
+
+        for _ in 0..10 {
+            breaker.record_close(dec("-1"));
+        }
+
+        assert!(!breaker.is_tripped());
     }
 }
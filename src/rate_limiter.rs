@@ -0,0 +1,51 @@
+use tokio::{
+    sync::Mutex,
+    time::{sleep, Duration, Instant},
+};
+
+pub struct Bucket {
+    pub tokens: f64,
+    pub last_refill: Instant,
+}
+
+pub struct RateLimiter {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+    pub bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: u32, burst_limit: u32) -> Self {
+        let capacity = burst_limit.max(1) as f64;
+
+        Self {
+            capacity,
+            refill_per_sec: requests_per_minute.max(1) as f64 / 60.0,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+
+            sleep(wait).await;
+        }
+    }
+}
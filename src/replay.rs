@@ -0,0 +1,50 @@
+use crate::data::{Candles, TradingBot};
+use anyhow::{anyhow, Result};
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+pub struct ReplaySource {
+    pub candles: Vec<Candles>,
+    pub speed: Option<u32>,
+}
+
+impl ReplaySource {
+    pub fn new(mut candles: Vec<Candles>, speed: Option<u32>) -> Self {
+        candles.sort_by_key(|c| c.timestamp);
+        Self { candles, speed }
+    }
+
+    pub async fn run(&self, bot: &TradingBot, symbol: &str) -> Result<()> {
+        if !bot.config().paper_trading {
+            return Err(anyhow!(
+                "Replay requires paper trading, refusing to replay into live execution"
+            ));
+        }
+
+        info!(
+            "Replaying {} candles for {} through the engine",
+            self.candles.len(),
+            symbol
+        );
+
+        if bot.config().book_depth.is_some() || bot.config().confirm_timeframe.is_some() {
+            warn!("Replay ignores book_depth and confirm_timeframe, they need live market data");
+        }
+
+        let mut previous: Option<i64> = None;
+
+        for candle in &self.candles {
+            if let (Some(speed), Some(prev)) = (self.speed, previous) {
+                let gap = (candle.timestamp - prev).max(0) as f64;
+                sleep(Duration::from_secs_f64(gap / speed.max(1) as f64)).await;
+            }
+
+            previous = Some(candle.timestamp);
+            bot.process_candle(candle.clone(), symbol).await?;
+        }
+
+        info!("Replay finished for {}", symbol);
+
+        Ok(())
+    }
+}
@@ -1,39 +1,486 @@
-use crate::data::{OrderReq, Side};
+use crate::data::{
+    BinanceAccount, BinanceBalance, BinanceDepth, BinanceExchangeInfo, BinanceListenKey,
+    BinanceOpenOrder, BinanceOrder, Candles, OpenOrder, OrderFill, OrderReq, OrderStatus, Side,
+    SymbolFilters, TimeInForce,
+};
+use crate::position_manager::round_to_tick;
+use crate::rate_limiter::RateLimiter;
 use crate::sign::signature;
 use anyhow::{anyhow, Result};
 use chrono::Utc;
-use reqwest::Client;
-use rust_decimal::Decimal;
-use tracing::info;
+use reqwest::{Client, Response};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use tokio::{
+    sync::RwLock,
+    time::{sleep, Duration},
+};
+use tracing::{info, warn};
+
+const MAX_ORDER_ATTEMPTS: u32 = 3;
+const ORDER_DOES_NOT_EXIST: &str = "-2013";
+
+async fn with_retry<F, Fut>(op: F, max_attempts: u32) -> reqwest::Result<Response>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = reqwest::Result<Response>>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(response) => return Ok(response),
+            Err(e) if attempt < max_attempts && e.is_connect() => {
+                let delay = Duration::from_millis(500 * 2u64.pow(attempt - 1));
+                warn!(
+                    "Request failed on attempt {}/{}: {}, retrying in {:?}",
+                    attempt, max_attempts, e, delay
+                );
+                sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn limit_order_query(req: &OrderReq, price: Decimal, timestamp: i64) -> String {
+    let side = match req.side {
+        Side::Buy => "BUY",
+        Side::Sell => "SELL",
+        Side::Hold => "HOLD",
+    };
+
+    let time_in_force = match req.time_in_force {
+        TimeInForce::Gtc => "GTC",
+        TimeInForce::Ioc => "IOC",
+        TimeInForce::Fok => "FOK",
+    };
+
+    format!(
+        "symbol={}&side={}&type=LIMIT&timeInForce={}&quantity={}&price={}&newClientOrderId={}&recvWindow=5000&timestamp={}",
+        req.symbol.replace("/", "").to_uppercase(),
+        side,
+        time_in_force,
+        req.size,
+        price,
+        req.id,
+        timestamp
+    )
+}
 
 pub struct BinanceClient {
     pub client: Client,
     pub base_url: String,
+    pub stream_url: String,
     pub api_key: String,
     pub api_secret: String,
+    pub rate_limiter: RateLimiter,
+    pub symbol_filters: RwLock<HashMap<String, SymbolFilters>>,
 }
 
 impl BinanceClient {
-    pub fn new(api_key: String, api_secret: String, testnet: bool) -> Self {
-        let base_url = if testnet {
-            "https://testnet.binance.vision".to_string()
+    pub fn new(
+        api_key: String,
+        api_secret: String,
+        testnet: bool,
+        rate_limiter: RateLimiter,
+    ) -> Self {
+        let (base_url, stream_url) = if testnet {
+            (
+                "https://testnet.binance.vision".to_string(),
+                "wss://testnet.binance.vision/ws".to_string(),
+            )
         } else {
-            "https://api.binance.com".to_string()
+            (
+                "https://api.binance.com".to_string(),
+                "wss://stream.binance.com:9443/ws".to_string(),
+            )
         };
 
         Self {
             client: Client::new(),
             base_url,
+            stream_url,
             api_key,
             api_secret,
+            rate_limiter,
+            symbol_filters: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn cached_filters(&self, symbol: &str) -> Option<SymbolFilters> {
+        self.symbol_filters.read().await.get(symbol).cloned()
+    }
+
+    pub async fn account_balance(&self, asset: &str) -> Result<Decimal> {
+        info!("Fetching account balance for asset: {}", asset);
+        let balances = self.balances().await?;
+        let balance = balances
+            .iter()
+            .find(|b| b.asset == asset)
+            .ok_or_else(|| anyhow!("No balance found for asset: {}", asset))?;
+
+        Ok(Decimal::from_str(&balance.free)?)
+    }
+
+    pub async fn asset_holding(&self, asset: &str) -> Result<Decimal> {
+        let balances = self.balances().await?;
+
+        match balances.iter().find(|b| b.asset == asset) {
+            Some(balance) => {
+                Ok(Decimal::from_str(&balance.free)? + Decimal::from_str(&balance.locked)?)
+            }
+            None => Ok(Decimal::ZERO),
         }
     }
 
-    pub async fn account_balance(&self) -> Result<Decimal> {
+    pub async fn balances(&self) -> Result<Vec<BinanceBalance>> {
         let url = format!("{}/api/v3/account", self.base_url);
-        let mock_data = signature(self.api_secret.as_bytes(), &url).await;
-        info!("Fetching account details: {:?}", mock_data);
-        Ok(Decimal::new(50000, 1))
+        let query_string = format!(
+            "recvWindow=5000&timestamp={}",
+            Utc::now().timestamp_millis()
+        );
+        let sign = signature(self.api_secret.as_bytes(), &query_string).await;
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .get(format!("{}?{}&signature={}", url, query_string, sign))
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while fetching account balance from Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        let account = response.json::<BinanceAccount>().await?;
+
+        Ok(account.balances)
+    }
+
+    pub async fn open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>> {
+        let exchange_symbol = symbol.replace("/", "").to_uppercase();
+        let url = format!("{}/api/v3/openOrders", self.base_url);
+        let query_string = format!(
+            "symbol={}&recvWindow=5000&timestamp={}",
+            exchange_symbol,
+            Utc::now().timestamp_millis()
+        );
+        let sign = signature(self.api_secret.as_bytes(), &query_string).await;
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .get(format!("{}?{}&signature={}", url, query_string, sign))
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while fetching open orders from Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        let orders = response.json::<Vec<BinanceOpenOrder>>().await?;
+
+        orders
+            .into_iter()
+            .map(|order| {
+                Ok(OpenOrder {
+                    order_id: order.order_id,
+                    client_order_id: order.client_order_id,
+                    symbol: symbol.to_string(),
+                    side: match order.side.as_str() {
+                        "BUY" => Side::Buy,
+                        "SELL" => Side::Sell,
+                        other => return Err(anyhow!("Unknown order side from Binance: {}", other)),
+                    },
+                    price: Decimal::from_str(&order.price)?,
+                    quantity: Decimal::from_str(&order.orig_qty)?,
+                    executed_quantity: Decimal::from_str(&order.executed_qty)?,
+                    status: OrderStatus::from_binance(&order.status)?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn query_order(&self, symbol: &str, client_order_id: &str) -> Result<OrderFill> {
+        self.find_order(symbol, client_order_id)
+            .await?
+            .ok_or_else(|| anyhow!("Order {} does not exist on Binance", client_order_id))
+    }
+
+    pub async fn find_order(
+        &self,
+        symbol: &str,
+        client_order_id: &str,
+    ) -> Result<Option<OrderFill>> {
+        let exchange_symbol = symbol.replace("/", "").to_uppercase();
+        let url = format!("{}/api/v3/order", self.base_url);
+        let query_string = format!(
+            "symbol={}&origClientOrderId={}&recvWindow=5000&timestamp={}",
+            exchange_symbol,
+            client_order_id,
+            Utc::now().timestamp_millis()
+        );
+        let sign = signature(self.api_secret.as_bytes(), &query_string).await;
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .get(format!("{}?{}&signature={}", url, query_string, sign))
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let body = response.text().await?;
+
+            if body.contains(ORDER_DOES_NOT_EXIST) {
+                return Ok(None);
+            }
+
+            return Err(anyhow!(
+                "Invalid response received while querying order from Binance: {}",
+                body
+            ));
+        }
+
+        let order = response.json::<BinanceOrder>().await?;
+        Ok(Some(OrderFill {
+            status: OrderStatus::from_binance(&order.status)?,
+            executed_qty: Decimal::from_str(&order.executed_qty)?,
+        }))
+    }
+
+    pub async fn start_user_stream(&self) -> Result<String> {
+        let url = format!("{}/api/v3/userDataStream", self.base_url);
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .post(url)
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while creating listen key at Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        Ok(response.json::<BinanceListenKey>().await?.listen_key)
+    }
+
+    pub async fn keepalive_user_stream(&self, listen_key: &str) -> Result<()> {
+        let url = format!(
+            "{}/api/v3/userDataStream?listenKey={}",
+            self.base_url, listen_key
+        );
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .put(url)
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while keeping listen key alive at Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn fetch_symbol_filters(&self, symbol: &str) -> Result<SymbolFilters> {
+        if let Some(filters) = self.cached_filters(symbol).await {
+            return Ok(filters);
+        }
+
+        let exchange_symbol = symbol.replace("/", "").to_uppercase();
+        let url = format!(
+            "{}/api/v3/exchangeInfo?symbol={}",
+            self.base_url, exchange_symbol
+        );
+        self.rate_limiter.acquire().await;
+        let response = self.client.get(url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while fetching exchange info from Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        let info = response.json::<BinanceExchangeInfo>().await?;
+        let filters = Self::parse_symbol_filters(&info, &exchange_symbol)?;
+
+        self.symbol_filters
+            .write()
+            .await
+            .insert(symbol.to_string(), filters.clone());
+
+        Ok(filters)
+    }
+
+    fn parse_symbol_filters(info: &BinanceExchangeInfo, symbol: &str) -> Result<SymbolFilters> {
+        let symbol_info = info
+            .symbols
+            .iter()
+            .find(|s| s.symbol == symbol)
+            .ok_or_else(|| anyhow!("No exchange info found for symbol: {}", symbol))?;
+
+        let mut filters = SymbolFilters {
+            step_size: Decimal::ZERO,
+            min_qty: Decimal::ZERO,
+            tick_size: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+        };
+        let parse = |value: &Option<String>| -> Result<Decimal> {
+            match value {
+                Some(raw) => Ok(Decimal::from_str(raw)?),
+                None => Ok(Decimal::ZERO),
+            }
+        };
+
+        for filter in &symbol_info.filters {
+            match filter.filter_type.as_str() {
+                "LOT_SIZE" => {
+                    filters.step_size = parse(&filter.step_size)?;
+                    filters.min_qty = parse(&filter.min_qty)?;
+                }
+                "PRICE_FILTER" => filters.tick_size = parse(&filter.tick_size)?,
+                "MIN_NOTIONAL" | "NOTIONAL" => filters.min_notional = parse(&filter.min_notional)?,
+                _ => {}
+            }
+        }
+
+        Ok(filters)
+    }
+
+    pub async fn order_book_imbalance(&self, symbol: &str, depth: u32) -> Result<f64> {
+        let symbol = symbol.replace("/", "").to_uppercase();
+        let url = format!(
+            "{}/api/v3/depth?symbol={}&limit={}",
+            self.base_url, symbol, depth
+        );
+        self.rate_limiter.acquire().await;
+        let response = self.client.get(url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while fetching order book from Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        let book = response.json::<BinanceDepth>().await?;
+        let volume = |levels: &[[String; 2]]| -> Result<Decimal> {
+            levels
+                .iter()
+                .map(|level| Ok(Decimal::from_str(&level[1])?))
+                .sum()
+        };
+        let bids = volume(&book.bids)?;
+        let asks = volume(&book.asks)?;
+
+        if (bids + asks).is_zero() {
+            return Ok(0.0);
+        }
+
+        Ok(((bids - asks) / (bids + asks)).to_f64().unwrap_or(0.0))
+    }
+
+    pub async fn recent_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        limit: u32,
+    ) -> Result<Vec<Candles>> {
+        let symbol = symbol.replace("/", "").to_uppercase();
+        let url = format!(
+            "{}/api/v3/klines?symbol={}&interval={}&limit={}",
+            self.base_url, symbol, interval, limit
+        );
+        self.rate_limiter.acquire().await;
+        let response = self.client.get(url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Invalid response received while fetching klines from Binance: {:?}",
+                response.text().await
+            ));
+        }
+
+        let rows = response.json::<Vec<Vec<serde_json::Value>>>().await?;
+
+        rows.iter()
+            .map(|row| {
+                let field = |i: usize| -> Result<Decimal> {
+                    let raw = row
+                        .get(i)
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| anyhow!("Malformed kline row: {:?}", row))?;
+                    Ok(Decimal::from_str(raw)?)
+                };
+
+                let open_time = row
+                    .first()
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| anyhow!("Malformed kline row: {:?}", row))?;
+
+                Ok(Candles {
+                    timestamp: open_time / 1000,
+                    open: field(1)?,
+                    high: field(2)?,
+                    low: field(3)?,
+                    close: field(4)?,
+                    volume: field(5)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn send_market_order(
+        &self,
+        symbol: &str,
+        side: &str,
+        req: &OrderReq,
+    ) -> reqwest::Result<Response> {
+        let body = format!(
+            "symbol={}&side={}&type=MARKET&quantity={}&newClientOrderId={}&recvWindow=5000&timestamp={}",
+            symbol,
+            side,
+            req.size,
+            req.id,
+            Utc::now().timestamp_millis()
+        );
+
+        let url = format!("{}/api/v3/order", self.base_url);
+        let sign = signature(self.api_secret.as_bytes(), &body).await;
+        let request_url = format!("{}?{}&signature={}", url, body, sign);
+        with_retry(
+            || async {
+                self.rate_limiter.acquire().await;
+                self.client
+                    .post(&request_url)
+                    .header("X-MBX-APIKEY", self.api_key.clone())
+                    .send()
+                    .await
+            },
+            MAX_ORDER_ATTEMPTS,
+        )
+        .await
     }
 
     pub async fn place_market_order(&self, req: &OrderReq) -> Result<String> {
@@ -56,23 +503,29 @@ impl BinanceClient {
             ));
         }
 
-        let body = format!(
-            "symbol={}&side={}&type=MARKET&quantity={}&newClientOrderId={}&recvWindow=5000&timestamp={}",
-            symbol,
-            side,
-            req.size,
-            req.id,
-            Utc::now().timestamp_millis()
-        );
+        let response = match self.send_market_order(&symbol, &side, req).await {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() => {
+                warn!(
+                    "Market order {} timed out, checking whether it reached Binance",
+                    req.id
+                );
 
-        let url = "https://testnet.binance.vision/api/v3/order";
-        let sign = signature(self.api_secret.as_bytes(), &body).await;
-        let response = self
-            .client
-            .post(format!("{}?{}&signature={}", url, body, sign))
-            .header("X-MBX-APIKEY", self.api_key.clone())
-            .send()
-            .await?;
+                match self.find_order(&req.symbol, &req.id).await? {
+                    Some(fill) => {
+                        info!("Market order {} was accepted as {:?}", req.id, fill.status);
+                        return Ok(serde_json::json!({
+                            "clientOrderId": req.id,
+                            "status": format!("{:?}", fill.status),
+                            "executedQty": fill.executed_qty.to_string(),
+                        })
+                        .to_string());
+                    }
+                    None => self.send_market_order(&symbol, &side, req).await?,
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
 
         if !response.status().is_success() {
             return Err(anyhow!(
@@ -90,13 +543,6 @@ impl BinanceClient {
             "placing limit order {:?} for {} of size {} @ {}",
             req.side, req.symbol, req.size, req.price
         );
-        let symbol = req.symbol.replace("/", "").to_uppercase();
-
-        let side = match req.side {
-            Side::Buy => "BUY".to_string(),
-            Side::Sell => "SELL".to_string(),
-            Side::Hold => "HOLD".to_string(),
-        };
 
         if req.size.is_zero() {
             return Err(anyhow!(
@@ -105,20 +551,19 @@ impl BinanceClient {
             ));
         }
 
-        let body = format!(
-            "symbol={}&side={}&type=MARKET&quantity={}&newClientOrderId={}&recvWindow=5000&timestamp={}",
-            symbol,
-            side,
-            req.size,
-            req.id,
-            Utc::now().timestamp_millis()
-        );
+        let price = match self.cached_filters(&req.symbol).await {
+            Some(filters) => round_to_tick(req.price, filters.tick_size),
+            None => req.price,
+        };
 
-        let url = "https://testnet.binance.vision/api/v3/order";
+        let body = limit_order_query(req, price, Utc::now().timestamp_millis());
+
+        let url = format!("{}/api/v3/order", self.base_url);
         let sign = signature(self.api_secret.as_bytes(), &body).await;
+        self.rate_limiter.acquire().await;
         let response = self
             .client
-            .post(format!("{}?{}&signature={:?}", url, body, sign))
+            .post(format!("{}?{}&signature={}", url, body, sign))
             .header("X-MBX-APIKEY", self.api_key.clone())
             .send()
             .await?;
@@ -135,21 +580,27 @@ impl BinanceClient {
     }
 
     pub async fn cancel_orders(&self, req: &OrderReq) -> Result<String> {
+        self.cancel_client_order(&req.symbol, &req.id).await
+    }
+
+    pub async fn cancel_client_order(&self, symbol: &str, client_order_id: &str) -> Result<String> {
         info!(
             "Cancelling the order for ID {} and symbol {}",
-            req.id, req.symbol
+            client_order_id, symbol
         );
-        let url = "https://testnet.binance.vision/api/v3/order";
+        let url = format!("{}/api/v3/order", self.base_url);
         let now = Utc::now().timestamp_millis().to_string();
-        let symbol = req.symbol.replace("/", "").to_uppercase();
+        let symbol = symbol.replace("/", "").to_uppercase();
         let query_string = format!(
-            "symbol={}&originClientOrderId={}&recvWindow=5000&timestamp={}",
-            symbol, req.id, now
+            "symbol={}&origClientOrderId={}&recvWindow=5000&timestamp={}",
+            symbol, client_order_id, now
         );
         let sign = signature(self.api_secret.as_bytes(), &query_string).await;
+        self.rate_limiter.acquire().await;
         let response = self
             .client
             .delete(format!("{}?{}&signature={}", url, query_string, sign))
+            .header("X-MBX-APIKEY", self.api_key.clone())
             .send()
             .await?;
 
@@ -164,3 +615,173 @@ impl BinanceClient {
         Ok(res.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn exchange_info(filters: &str) -> BinanceExchangeInfo {
+        serde_json::from_str(&format!(
+            r#"{{"symbols":[{{"symbol":"ETHUSDT","filters":[{}]}}]}}"#,
+            filters
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_symbol_filters_reads_lot_price_and_notional() {
+        let info = exchange_info(
+            r#"{"filterType":"PRICE_FILTER","minPrice":"0.01","maxPrice":"1000000","tickSize":"0.01"},
+               {"filterType":"LOT_SIZE","minQty":"0.0001","maxQty":"9000","stepSize":"0.0001"},
+               {"filterType":"NOTIONAL","minNotional":"5","applyMinToMarket":true},
+               {"filterType":"ICEBERG_PARTS","limit":10}"#,
+        );
+        let filters = BinanceClient::parse_symbol_filters(&info, "ETHUSDT").unwrap();
+
+        assert_eq!(filters.tick_size, Decimal::new(1, 2));
+        assert_eq!(filters.step_size, Decimal::new(1, 4));
+        assert_eq!(filters.min_qty, Decimal::new(1, 4));
+        assert_eq!(filters.min_notional, Decimal::new(5, 0));
+    }
+
+    #[test]
+    fn parse_symbol_filters_reads_legacy_min_notional() {
+        let info = exchange_info(r#"{"filterType":"MIN_NOTIONAL","minNotional":"10.00"}"#);
+        let filters = BinanceClient::parse_symbol_filters(&info, "ETHUSDT").unwrap();
+
+        assert_eq!(filters.min_notional, Decimal::new(10, 0));
+        assert_eq!(filters.step_size, Decimal::ZERO);
+        assert_eq!(filters.tick_size, Decimal::ZERO);
+    }
+
+    #[test]
+    fn parse_symbol_filters_rejects_unknown_symbol() {
+        let info = exchange_info("");
+
+        assert!(BinanceClient::parse_symbol_filters(&info, "BTCUSDT").is_err());
+    }
+
+    #[test]
+    fn parse_symbol_filters_rejects_bad_decimal() {
+        let info = exchange_info(r#"{"filterType":"LOT_SIZE","minQty":"x","stepSize":"0.1"}"#);
+
+        assert!(BinanceClient::parse_symbol_filters(&info, "ETHUSDT").is_err());
+    }
+
+    fn limit_req() -> OrderReq {
+        OrderReq {
+            id: "entry-1".to_string(),
+            symbol: "ETH/USDT".to_string(),
+            side: Side::Buy,
+            order_type: crate::data::OrderType::Limit,
+            time_in_force: TimeInForce::default(),
+            price: Decimal::new(250012, 2),
+            size: Decimal::new(15, 2),
+            sl: None,
+            tp: None,
+            manual: false,
+        }
+    }
+
+    #[test]
+    fn limit_order_query_sends_limit_type_and_price() {
+        let query = limit_order_query(&limit_req(), Decimal::new(250012, 2), 1_700_000_000_000);
+
+        assert_eq!(
+            query,
+            "symbol=ETHUSDT&side=BUY&type=LIMIT&timeInForce=GTC&quantity=0.15&price=2500.12&newClientOrderId=entry-1&recvWindow=5000&timestamp=1700000000000"
+        );
+    }
+
+    #[test]
+    fn limit_order_query_uses_the_rounded_price() {
+        let query = limit_order_query(&limit_req(), Decimal::new(2500, 0), 1);
+
+        assert!(query.contains("&price=2500&"));
+        assert!(!query.contains("type=MARKET"));
+    }
+
+    #[test]
+    fn limit_order_query_serializes_time_in_force() {
+        for (time_in_force, expected) in [
+            (TimeInForce::Gtc, "&timeInForce=GTC&"),
+            (TimeInForce::Ioc, "&timeInForce=IOC&"),
+            (TimeInForce::Fok, "&timeInForce=FOK&"),
+        ] {
+            let req = OrderReq {
+                time_in_force,
+                ..limit_req()
+            };
+
+            assert!(limit_order_query(&req, req.price, 1).contains(expected));
+        }
+    }
+
+    async fn closed_port() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    async fn ok_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+
+            if let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn with_retry_retries_network_errors_until_success() {
+        let client = Client::new();
+        let down = closed_port().await;
+        let up = ok_server().await;
+        let attempts = AtomicU32::new(0);
+
+        let response = with_retry(
+            || {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                let url = if attempt < 2 {
+                    down.clone()
+                } else {
+                    up.clone()
+                };
+                client.get(url).send()
+            },
+            MAX_ORDER_ATTEMPTS,
+        )
+        .await
+        .unwrap();
+
+        assert!(response.status().is_success());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn with_retry_gives_up_after_max_attempts() {
+        let client = Client::new();
+        let down = closed_port().await;
+        let attempts = AtomicU32::new(0);
+
+        let result = with_retry(
+            || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                client.get(&down).send()
+            },
+            2,
+        )
+        .await;
+
+        assert!(result.unwrap_err().is_connect());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
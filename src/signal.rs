@@ -1,24 +1,150 @@
-use crate::data::{Candles, Side, Signal, Trend};
+use crate::{
+    config::IndicatorConfig,
+    data::{Candles, ConfidenceBreakdown, Side, Signal, Trend},
+};
 use rust_decimal::prelude::*;
 use uuid::Uuid;
 
+const BOOK_PRESSURE_THRESHOLD: f64 = 0.3;
+
 pub struct MarketSignal {
     pub candles: Vec<Candles>,
     pub rsi: usize,
     pub ema_slow: usize,
     pub ema_fast: usize,
+    pub macd_signal: usize,
+    pub atr: usize,
+    pub obv: usize,
+    pub trend_fast: usize,
+    pub trend_slow: usize,
+    pub min_candles: usize,
+    pub book_pressure: Option<f64>,
+    pub higher_trend: Option<Trend>,
+    pub higher_period: Option<i64>,
+    pub higher_unavailable: bool,
+    pub keltner_period: usize,
+    pub keltner_atr_mult: Option<Decimal>,
 }
 
 impl MarketSignal {
-    pub fn new() -> Self {
+    pub fn with_params(
+        rsi: usize,
+        ema_fast: usize,
+        ema_slow: usize,
+        trend_fast: usize,
+        trend_slow: usize,
+        min_candles: usize,
+    ) -> Self {
         Self {
             candles: Vec::new(),
-            rsi: 14,
-            ema_slow: 26,
-            ema_fast: 12,
+            rsi,
+            ema_slow,
+            ema_fast,
+            macd_signal: 9,
+            atr: 14,
+            obv: 20,
+            trend_fast,
+            trend_slow,
+            min_candles,
+            book_pressure: None,
+            higher_trend: None,
+            higher_period: None,
+            higher_unavailable: false,
+            keltner_period: 20,
+            keltner_atr_mult: None,
+        }
+    }
+
+    pub fn set_higher_timeframe(&mut self, period: Option<i64>, candles: Option<Vec<Candles>>) {
+        self.higher_period = period;
+        self.higher_unavailable = candles.is_none();
+        self.higher_trend = candles.map(|candles| {
+            let higher = MarketSignal {
+                candles,
+                ..MarketSignal::with_params(
+                    self.rsi,
+                    self.ema_fast,
+                    self.ema_slow,
+                    self.trend_fast,
+                    self.trend_slow,
+                    self.min_candles,
+                )
+            };
+            higher.detect_trend()
+        });
+    }
+
+    pub fn clear_higher_timeframe(&mut self) {
+        self.higher_trend = None;
+        self.higher_period = None;
+        self.higher_unavailable = false;
+    }
+
+    fn confirm_higher_timeframe(&self, action: Side) -> Side {
+        match (&action, &self.higher_trend) {
+            (Side::Buy | Side::Sell, _) if self.higher_unavailable => Side::Hold,
+            (Side::Buy, Some(trend)) if *trend != Trend::Up => Side::Hold,
+            (Side::Sell, Some(trend)) if *trend != Trend::Down => Side::Hold,
+            _ => action,
+        }
+    }
+
+    fn confirm_keltner(&self, action: Side) -> Side {
+        let Some(multiplier) = self.keltner_atr_mult else {
+            return action;
+        };
+
+        let (upper, _, lower) =
+            self.calculate_keltner(self.keltner_period, self.keltner_period, multiplier);
+
+        match (&action, upper.last(), lower.last(), self.candles.last()) {
+            (Side::Buy, Some(upper), _, Some(candle)) if candle.close > *upper => Side::Hold,
+            (Side::Sell, _, Some(lower), Some(candle)) if candle.close < *lower => Side::Hold,
+            _ => action,
+        }
+    }
+
+    pub fn set_book_pressure(&mut self, imbalance: Option<f64>) {
+        self.book_pressure = imbalance.map(|i| i.clamp(-1.0, 1.0));
+    }
+
+    fn bid_pressure(&self) -> bool {
+        self.book_pressure
+            .is_some_and(|i| i >= BOOK_PRESSURE_THRESHOLD)
+    }
+
+    fn ask_pressure(&self) -> bool {
+        self.book_pressure
+            .is_some_and(|i| i <= -BOOK_PRESSURE_THRESHOLD)
+    }
+
+    pub fn from_config(config: &IndicatorConfig) -> Self {
+        Self {
+            keltner_period: config.keltner_period,
+            keltner_atr_mult: config.keltner_atr_mult,
+            ..Self::with_params(
+                config.rsi,
+                config.ema_fast,
+                config.ema_slow,
+                config.trend_fast,
+                config.trend_slow,
+                config.min_candles,
+            )
         }
     }
 
+    pub fn apply_indicators(&mut self, config: &IndicatorConfig) {
+        self.rsi = config.rsi;
+        self.ema_fast = config.ema_fast;
+        self.ema_slow = config.ema_slow;
+        self.trend_fast = config.trend_fast;
+        self.trend_slow = config.trend_slow;
+        self.min_candles = config.min_candles;
+        self.keltner_period = config.keltner_period;
+        self.keltner_atr_mult = config.keltner_atr_mult;
+        self.higher_period = None;
+    }
+
     pub fn add_candles(&mut self, candle: Candles) {
         self.candles.push(candle);
 
@@ -58,55 +184,202 @@ impl MarketSignal {
         100.0 - (100.0 / (1.0 + rs))
     }
 
-    pub fn calculate_ema(&self, period: usize) -> Decimal {
-        if self.candles.is_empty() {
-            return Decimal::ZERO;
+    pub fn calculate_obv(&self) -> Vec<f64> {
+        let mut series = Vec::with_capacity(self.candles.len());
+        let mut obv = 0.0;
+
+        if !self.candles.is_empty() {
+            series.push(obv);
         }
 
-        let multiplier = Decimal::new(2, 0) / Decimal::new((period + 1) as i64, 0);
-        let mut ema = self.candles[0].close;
+        for pair in self.candles.windows(2) {
+            let volume = pair[1].volume.to_f64().unwrap_or(0.0);
+
+            if pair[1].close > pair[0].close {
+                obv += volume;
+            } else if pair[1].close < pair[0].close {
+                obv -= volume;
+            }
 
-        for candle in self.candles.iter().skip(1) {
-            ema = (candle.close - ema) * multiplier + ema;
+            series.push(obv);
         }
 
-        ema
+        series
     }
 
-    pub fn calculate_macd(&self) -> (f64, f64) {
-        let ema_fast = self.calculate_ema(self.ema_fast).to_f64().unwrap();
-        let ema_slow = self.calculate_ema(self.ema_slow).to_f64().unwrap();
-        let macd = ema_fast - ema_slow;
-        let signal = macd * 0.8;
-        (macd, signal)
+    fn obv_confirms(&self, trend: &Trend) -> bool {
+        let obv = self.calculate_obv();
+
+        if obv.len() <= self.obv {
+            return false;
+        }
+
+        let change = obv[obv.len() - 1] - obv[obv.len() - 1 - self.obv];
+
+        match trend {
+            Trend::Up => change > 0.0,
+            Trend::Down => change < 0.0,
+            Trend::Sideways => false,
+        }
     }
 
-    pub fn calculate_confidence(&self, rsi: f64, macd: f64, trend: &Trend) -> f64 {
-        let mut confidence = 0.5;
-        if !(30.0..70.0).contains(&rsi) {
-            confidence += 0.2;
+    fn true_ranges(&self) -> Vec<Decimal> {
+        self.candles
+            .windows(2)
+            .map(|pair| {
+                let prev_close = pair[0].close;
+                let candle = &pair[1];
+                (candle.high - candle.low)
+                    .max((candle.high - prev_close).abs())
+                    .max((candle.low - prev_close).abs())
+            })
+            .collect()
+    }
+
+    pub fn calculate_atr(&self) -> Option<Decimal> {
+        let true_ranges = self.true_ranges();
+
+        if self.atr == 0 || true_ranges.len() < self.atr {
+            return None;
         }
-        if macd.abs() > 0.01 {
-            confidence += 0.15;
+
+        let total: Decimal = true_ranges[true_ranges.len() - self.atr..].iter().sum();
+        Some(total / Decimal::new(self.atr as i64, 0))
+    }
+
+    pub fn calculate_keltner(
+        &self,
+        ema_period: usize,
+        atr_period: usize,
+        multiplier: Decimal,
+    ) -> (Vec<Decimal>, Vec<Decimal>, Vec<Decimal>) {
+        let mut upper = Vec::new();
+        let mut middle = Vec::new();
+        let mut lower = Vec::new();
+        let true_ranges = self.true_ranges();
+
+        if atr_period == 0
+            || ema_period == 0
+            || true_ranges.len() < atr_period
+            || self.candles.len() < ema_period
+        {
+            return (upper, middle, lower);
         }
-        if *trend != Trend::Sideways {
-            confidence += 0.15;
+
+        let closes: Vec<Decimal> = self.candles.iter().map(|c| c.close).collect();
+        let ema = Self::ema_series(&closes, ema_period);
+
+        for end in atr_period.max(ema_period - 1)..=true_ranges.len() {
+            let atr = true_ranges[end - atr_period..end].iter().sum::<Decimal>()
+                / Decimal::new(atr_period as i64, 0);
+            let mid = ema[end + 1 - ema_period];
+
+            upper.push(mid + atr * multiplier);
+            middle.push(mid);
+            lower.push(mid - atr * multiplier);
+        }
+
+        (upper, middle, lower)
+    }
+
+    fn ema_series(values: &[Decimal], period: usize) -> Vec<Decimal> {
+        let mut series = Vec::new();
+
+        if period == 0 || values.len() < period {
+            return series;
+        }
+
+        let multiplier = Decimal::new(2, 0) / Decimal::new((period + 1) as i64, 0);
+        let mut ema = values[..period].iter().sum::<Decimal>() / Decimal::new(period as i64, 0);
+        series.push(ema);
+
+        for value in values.iter().skip(period) {
+            ema = (*value - ema) * multiplier + ema;
+            series.push(ema);
+        }
+
+        series
+    }
+
+    pub fn calculate_ema(&self, period: usize) -> Decimal {
+        let closes: Vec<Decimal> = self.candles.iter().map(|c| c.close).collect();
+
+        Self::ema_series(&closes, period)
+            .last()
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn calculate_macd(&self) -> (f64, f64) {
+        let closes: Vec<Decimal> = self.candles.iter().map(|c| c.close).collect();
+        let ema_fast = Self::ema_series(&closes, self.ema_fast);
+        let ema_slow = Self::ema_series(&closes, self.ema_slow);
+
+        let offset = ema_fast.len().saturating_sub(ema_slow.len());
+
+        let macd_line: Vec<Decimal> = ema_fast
+            .iter()
+            .skip(offset)
+            .zip(ema_slow.iter())
+            .map(|(fast, slow)| fast - slow)
+            .collect();
+        let signal_line = Self::ema_series(&macd_line, self.macd_signal);
+
+        match (macd_line.last(), signal_line.last()) {
+            (Some(macd), Some(signal)) => {
+                (macd.to_f64().unwrap_or(0.0), signal.to_f64().unwrap_or(0.0))
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    pub fn confidence_breakdown(&self, rsi: f64, macd: f64, trend: &Trend) -> ConfidenceBreakdown {
+        let base = 0.5;
+        let rsi_component = if !(30.0..70.0).contains(&rsi) {
+            0.2
+        } else {
+            0.0
+        };
+        let macd_component = if macd.abs() > 0.01 { 0.15 } else { 0.0 };
+        let trend_component = if *trend != Trend::Sideways { 0.15 } else { 0.0 };
+        let volume_component = if self.obv_confirms(trend) { 0.1 } else { 0.0 };
+        let book_component = match trend {
+            Trend::Up if self.bid_pressure() => 0.1,
+            Trend::Down if self.ask_pressure() => 0.1,
+            _ => 0.0,
+        };
+        let total = f64::min(
+            base + rsi_component
+                + macd_component
+                + trend_component
+                + volume_component
+                + book_component,
+            1.0,
+        );
+
+        ConfidenceBreakdown {
+            base,
+            rsi_component,
+            macd_component,
+            trend_component,
+            volume_component,
+            book_component,
+            total,
         }
-        confidence
     }
 
     pub fn detect_trend(&self) -> Trend {
-        if self.candles.len() < 50 {
+        if self.candles.len() < self.trend_slow || self.candles.is_empty() {
             return Trend::Sideways;
         }
 
-        let ema_20 = self.calculate_ema(20);
-        let ema_50 = self.calculate_ema(50);
+        let ema_fast = self.calculate_ema(self.trend_fast);
+        let ema_slow = self.calculate_ema(self.trend_slow);
         let recent_close = self.candles.last().unwrap().close;
 
-        if recent_close > ema_20 && ema_20 > ema_50 {
+        if recent_close > ema_fast && ema_fast > ema_slow {
             Trend::Up
-        } else if recent_close < ema_20 && ema_20 < ema_50 {
+        } else if recent_close < ema_fast && ema_fast < ema_slow {
             Trend::Down
         } else {
             Trend::Sideways
@@ -114,27 +387,30 @@ impl MarketSignal {
     }
 
     pub fn determine_action(&self, rsi: f64, macd: f64, signal_line: f64) -> Side {
+        let oversold = if self.bid_pressure() { 40.0 } else { 30.0 };
+        let overbought = if self.ask_pressure() { 60.0 } else { 70.0 };
+
         match self.detect_trend() {
             Trend::Up => {
-                if rsi < 30.0 && macd > signal_line {
+                if rsi < oversold && macd > signal_line {
                     Side::Buy
-                } else if rsi > 70.0 {
+                } else if rsi > overbought {
                     Side::Sell
                 } else {
                     Side::Hold
                 }
             }
             Trend::Down => {
-                if rsi > 70.0 && macd < signal_line {
+                if rsi > overbought && macd < signal_line {
                     Side::Sell
                 } else {
                     Side::Hold
                 }
             }
             Trend::Sideways => {
-                if rsi < 30.0 {
+                if rsi < oversold {
                     Side::Buy
-                } else if rsi > 70.0 {
+                } else if rsi > overbought {
                     Side::Sell
                 } else {
                     Side::Hold
@@ -144,18 +420,25 @@ impl MarketSignal {
     }
 
     pub fn analyze(&self, symbol: String) -> Option<Signal> {
-        if self.candles.len() < 50 {
+        self.analyze_detailed(symbol).map(|(signal, _)| signal)
+    }
+
+    pub fn analyze_detailed(&self, symbol: String) -> Option<(Signal, ConfidenceBreakdown)> {
+        if self.candles.len() < self.min_candles.max(1) {
             return None;
         }
 
         let trend = self.detect_trend();
         let rsi = self.calculate_rsi();
         let (macd, signal) = self.calculate_macd();
-        let action = self.determine_action(rsi, macd, signal);
+        let action = self.confirm_keltner(
+            self.confirm_higher_timeframe(self.determine_action(rsi, macd, signal)),
+        );
         let latest_candle = self.candles.last()?;
-        let confidence = Decimal::from_f64(self.calculate_confidence(rsi, macd, &trend)).unwrap();
+        let breakdown = self.confidence_breakdown(rsi, macd, &trend);
+        let confidence = Decimal::from_f64(breakdown.total).unwrap();
 
-        Some(Signal {
+        let signal = Signal {
             id: Uuid::new_v4().to_string(),
             timestamp: latest_candle.timestamp,
             symbol,
@@ -163,6 +446,184 @@ impl MarketSignal {
             trend: trend.clone(),
             price: latest_candle.close,
             confidence,
-        })
+        };
+
+        Some((signal, breakdown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn candle(timestamp: i64, close: i64) -> Candles {
+        let close = Decimal::from(close);
+
+        Candles {
+            open: close,
+            high: close + Decimal::ONE,
+            low: close - Decimal::ONE,
+            close,
+            volume: Decimal::from(10),
+            timestamp,
+        }
+    }
+
+    fn signal_with(closes: &[i64]) -> MarketSignal {
+        let mut signal = MarketSignal::with_params(14, 12, 26, 20, 50, 0);
+
+        for (i, close) in closes.iter().enumerate() {
+            signal.add_candles(candle(i as i64, *close));
+        }
+
+        signal
+    }
+
+    #[test]
+    fn macd_signal_line_matches_hand_computed_series() {
+        let mut signal = signal_with(&[1, 2, 3, 7, 5, 9, 11]);
+        signal.ema_fast = 1;
+        signal.ema_slow = 3;
+        signal.macd_signal = 3;
+
+        assert_eq!(signal.calculate_macd(), (2.0625, 1.875));
+    }
+
+    #[test]
+    fn macd_is_zero_without_enough_candles() {
+        let signal = signal_with(&[1, 2, 3]);
+
+        assert_eq!(signal.calculate_macd(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn fast_ema_leads_slow_ema_on_a_trend() {
+        let rising: Vec<Decimal> = (1..=100).map(Decimal::from).collect();
+        let falling: Vec<Decimal> = rising.iter().rev().copied().collect();
+
+        let fast = MarketSignal::ema_series(&rising, 20);
+        let slow = MarketSignal::ema_series(&rising, 50);
+        assert_eq!(fast.len(), 81);
+        assert_eq!(slow.len(), 51);
+        assert!(fast.last() > slow.last());
+        assert!(*fast.last().unwrap() < Decimal::from(100));
+
+        let fast = MarketSignal::ema_series(&falling, 20);
+        let slow = MarketSignal::ema_series(&falling, 50);
+        assert!(fast.last() < slow.last());
+    }
+
+    #[test]
+    fn ema_series_seeds_with_the_simple_average() {
+        let values: Vec<Decimal> = [2, 4, 6, 10].into_iter().map(Decimal::from).collect();
+
+        assert_eq!(
+            MarketSignal::ema_series(&values, 3),
+            vec![Decimal::from(4), Decimal::from(7)]
+        );
+        assert!(MarketSignal::ema_series(&values, 5).is_empty());
+    }
+
+    #[test]
+    fn keltner_bands_match_hand_computed_series() {
+        let signal = signal_with(&[1, 2, 3, 7, 6, 7, 11]);
+        let (upper, middle, lower) = signal.calculate_keltner(3, 3, Decimal::from(2));
+
+        assert_eq!(
+            middle,
+            vec![dec("4.5"), dec("5.25"), dec("6.125"), dec("8.5625")]
+        );
+        assert_eq!(
+            upper,
+            vec![dec("10.5"), dec("11.25"), dec("12.125"), dec("14.5625")]
+        );
+        assert_eq!(
+            lower,
+            vec![dec("-1.5"), dec("-0.75"), dec("0.125"), dec("2.5625")]
+        );
+    }
+
+    #[test]
+    fn keltner_holds_buys_above_the_upper_band() {
+        let mut signal = signal_with(&[1, 2, 3, 7, 6, 7, 11]);
+        signal.keltner_period = 3;
+
+        signal.keltner_atr_mult = Some(Decimal::from(2));
+        assert_eq!(signal.confirm_keltner(Side::Buy), Side::Buy);
+
+        signal.keltner_atr_mult = Some(dec("0.5"));
+        assert_eq!(signal.confirm_keltner(Side::Buy), Side::Hold);
+        assert_eq!(signal.confirm_keltner(Side::Sell), Side::Sell);
+
+        signal.keltner_atr_mult = None;
+        assert_eq!(signal.confirm_keltner(Side::Buy), Side::Buy);
+    }
+
+    #[test]
+    fn obv_rises_on_up_closes() {
+        let mut signal = signal_with(&[10, 11, 12, 12, 13]);
+
+        assert_eq!(signal.calculate_obv(), vec![0.0, 10.0, 20.0, 20.0, 30.0]);
+
+        signal.obv = 3;
+        assert!(signal.obv_confirms(&Trend::Up));
+        assert!(!signal.obv_confirms(&Trend::Down));
+    }
+
+    #[test]
+    fn obv_falls_on_down_closes() {
+        let signal = signal_with(&[13, 12, 11]);
+
+        assert_eq!(signal.calculate_obv(), vec![0.0, -10.0, -20.0]);
+    }
+
+    fn component_sum(breakdown: &ConfidenceBreakdown) -> f64 {
+        breakdown.base
+            + breakdown.rsi_component
+            + breakdown.macd_component
+            + breakdown.trend_component
+            + breakdown.volume_component
+            + breakdown.book_component
+    }
+
+    #[test]
+    fn confidence_components_sum_to_total() {
+        let mut signal = signal_with(&[10, 11, 12, 13, 14]);
+        signal.obv = 3;
+
+        let breakdown = signal.confidence_breakdown(50.0, 0.02, &Trend::Up);
+
+        assert_eq!(breakdown.rsi_component, 0.0);
+        assert_eq!(breakdown.volume_component, 0.1);
+        assert!((component_sum(&breakdown) - breakdown.total).abs() < 1e-9);
+        assert!((breakdown.total - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn overbought_rsi_adds_its_component() {
+        let signal = signal_with(&[10]);
+
+        let neutral = signal.confidence_breakdown(50.0, 0.0, &Trend::Sideways);
+        let overbought = signal.confidence_breakdown(80.0, 0.0, &Trend::Sideways);
+
+        assert_eq!(overbought.rsi_component, 0.2);
+        assert!((overbought.total - neutral.total - 0.2).abs() < 1e-9);
+        assert!((component_sum(&overbought) - overbought.total).abs() < 1e-9);
+    }
+
+    #[test]
+    fn confidence_total_is_capped_at_one() {
+        let mut signal = signal_with(&[10, 11, 12, 13, 14]);
+        signal.obv = 3;
+        signal.set_book_pressure(Some(0.5));
+
+        let breakdown = signal.confidence_breakdown(80.0, 0.02, &Trend::Up);
+
+        assert!(component_sum(&breakdown) > 1.0);
+        assert_eq!(breakdown.total, 1.0);
     }
 }
@@ -1,27 +1,184 @@
-use crate::data::{BinanceKlineEvent, Candles};
-use anyhow::{Context, Result};
-use futures_util::StreamExt;
+use crate::{
+    data::{BinanceExecutionReport, BinanceKlineEvent, Candles, OrderStatus, OrderUpdate, Side},
+    metrics::METRICS,
+};
+use anyhow::{anyhow, Context, Result};
+use futures_util::{
+    stream::{self, BoxStream},
+    Sink, SinkExt, Stream, StreamExt,
+};
 use rust_decimal::Decimal;
 use std::str::FromStr;
+use tokio::{
+    sync::mpsc,
+    time::{interval, sleep, Duration, MissedTickBehavior},
+};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tracing::{info, warn};
 
+fn spawn_heartbeat<S>(mut write: S, every: Duration) -> mpsc::UnboundedSender<Message>
+where
+    S: Sink<Message> + Unpin + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+
+    tokio::spawn(async move {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        loop {
+            let msg = tokio::select! {
+                _ = ticker.tick() => Message::Ping(Default::default()),
+                reply = rx.recv() => match reply {
+                    Some(reply) => reply,
+                    None => break,
+                },
+            };
+
+            if write.send(msg).await.is_err() {
+                warn!("Failed to send WebSocket heartbeat, stopping heartbeat task");
+                break;
+            }
+        }
+    });
+
+    tx
+}
+
+pub enum KlineEvent {
+    Candle(Candles),
+    Reconnected,
+}
+
+pub struct ResilientState {
+    pub client: WebSocketClient,
+    pub stream: Option<BoxStream<'static, Result<Candles>>>,
+    pub backoff: Duration,
+    pub connected: bool,
+}
+
+pub struct UserDataClient {
+    pub url: String,
+    pub heartbeat: Duration,
+}
+
+impl UserDataClient {
+    pub fn new(stream_url: &str, listen_key: &str, heartbeat: Duration) -> Self {
+        Self {
+            url: format!("{}/{}", stream_url, listen_key),
+            heartbeat,
+        }
+    }
+
+    fn parse_execution_report(text: &str) -> Result<Option<OrderUpdate>> {
+        let value: serde_json::Value = serde_json::from_str(text)?;
+
+        if value.get("e").and_then(|e| e.as_str()) != Some("executionReport") {
+            return Ok(None);
+        }
+
+        let report: BinanceExecutionReport = serde_json::from_value(value)?;
+        let client_order_id = if report.original_client_order_id.is_empty() {
+            report.client_order_id
+        } else {
+            report.original_client_order_id
+        };
+
+        Ok(Some(OrderUpdate {
+            symbol: report.symbol,
+            client_order_id,
+            side: match report.side.as_str() {
+                "BUY" => Side::Buy,
+                "SELL" => Side::Sell,
+                other => return Err(anyhow!("Unknown order side in execution report: {}", other)),
+            },
+            status: OrderStatus::from_binance(&report.status)?,
+            last_filled_qty: Decimal::from_str(&report.last_filled_qty)?,
+            last_filled_price: Decimal::from_str(&report.last_filled_price)?,
+            cumulative_filled_qty: Decimal::from_str(&report.cumulative_filled_qty)?,
+        }))
+    }
+
+    pub async fn connect(&self) -> Result<impl Stream<Item = Result<OrderUpdate>>> {
+        let (ws_stream, _) = connect_async(&self.url)
+            .await
+            .context("User data stream connection failed")?;
+
+        info!("Connected to Binance user data stream");
+
+        let (write, read) = ws_stream.split();
+        let pong_tx = spawn_heartbeat(write, self.heartbeat);
+        let stream = read.filter_map(move |msg| {
+            let pong_tx = pong_tx.clone();
+            async move {
+                match msg {
+                    Ok(Message::Ping(payload)) => {
+                        let _ = pong_tx.send(Message::Pong(payload));
+                        None
+                    }
+                    Ok(Message::Text(text)) => match Self::parse_execution_report(&text) {
+                        Ok(update) => update.map(Ok),
+                        Err(e) => {
+                            warn!("Failed to parse user data event: {}", e);
+                            None
+                        }
+                    },
+                    Ok(Message::Close(frame)) => {
+                        info!("User data stream closed by peer: {:?}", frame);
+                        None
+                    }
+                    Err(e) => Some(Err(anyhow!("User data stream error: {}", e))),
+                    _ => None,
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+}
+
 pub struct WebSocketClient {
     pub url: String,
+    pub heartbeat: Duration,
 }
 
 impl WebSocketClient {
-    pub fn new(symbol: &str, interval: &str) -> Self {
+    pub fn new(stream_url: &str, symbol: &str, interval: &str, heartbeat: Duration) -> Self {
         let symbol_lower = symbol.to_lowercase().replace("/", "");
         let url = format!(
-            "wss://stream.binance.com:9443/ws/{}@kline_{}",
+            "{}/{}@kline_{}",
+            stream_url,
             symbol_lower,
             interval.to_lowercase()
         );
 
         info!("ws url: {}", url);
 
-        Self { url }
+        Self { url, heartbeat }
+    }
+
+    fn parse_kline_event(text: &str) -> Result<Option<BinanceKlineEvent>> {
+        let mut value: serde_json::Value = serde_json::from_str(text)?;
+
+        if value.get("stream").is_some() {
+            value = value
+                .get_mut("data")
+                .map(serde_json::Value::take)
+                .ok_or_else(|| anyhow!("Combined stream message without data: {}", text))?;
+        }
+
+        if value.get("e").and_then(|e| e.as_str()) != Some("kline") {
+            return Ok(None);
+        }
+
+        let event: BinanceKlineEvent = serde_json::from_value(value)?;
+
+        if !event.kline.is_closed {
+            return Ok(None);
+        }
+
+        Ok(Some(event))
     }
 
     pub async fn connect(&self) -> Result<impl StreamExt<Item = Result<Candles, anyhow::Error>>> {
@@ -34,50 +191,212 @@ impl WebSocketClient {
             response.status()
         );
 
-        let (_, read) = ws_srteam.split();
-        let stream = read.filter_map(|msg| async move {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    let evt: BinanceKlineEvent = match serde_json::from_str(&text) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            warn!("Failed to parse raw json from WebSocket stream: {}", e);
-                            return None;
+        let (write, read) = ws_srteam.split();
+        let pong_tx = spawn_heartbeat(write, self.heartbeat);
+        let stream = read.filter_map(move |msg| {
+            let pong_tx = pong_tx.clone();
+            async move {
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        let evt = match Self::parse_kline_event(&text) {
+                            Ok(Some(evt)) => evt,
+                            Ok(None) => return None,
+                            Err(e) => {
+                                warn!("Failed to parse raw json from WebSocket stream: {}", e);
+                                return None;
+                            }
+                        };
+
+                        let k = evt.kline;
+
+                        if let (Ok(open), Ok(high), Ok(low), Ok(close), Ok(volume)) = (
+                            Decimal::from_str(&k.open),
+                            Decimal::from_str(&k.high),
+                            Decimal::from_str(&k.low),
+                            Decimal::from_str(&k.close),
+                            Decimal::from_str(&k.volume),
+                        ) {
+                            Some(Ok(Candles {
+                                timestamp: k.open_time / 1000,
+                                open,
+                                high,
+                                low,
+                                close,
+                                volume,
+                            }))
+                        } else {
+                            warn!("Failed to parse OHLCV decimals from kline: {:?}", k);
+                            None
                         }
-                    };
-
-                    let k = evt.kline;
-
-                    if let (Ok(open), Ok(high), Ok(low), Ok(close), Ok(volume)) = (
-                        Decimal::from_str(&k.open),
-                        Decimal::from_str(&k.high),
-                        Decimal::from_str(&k.low),
-                        Decimal::from_str(&k.close),
-                        Decimal::from_str(&k.volume),
-                    ) {
-                        Some(Ok(Candles {
-                            timestamp: k.open_time / 1000,
-                            open,
-                            high,
-                            low,
-                            close,
-                            volume,
-                        }))
-                    } else {
-                        warn!("Failed to parse OHLCV decimals from kline: {:?}", k);
+                    }
+                    Ok(Message::Ping(payload)) => {
+                        let _ = pong_tx.send(Message::Pong(payload));
                         None
                     }
+                    Ok(Message::Pong(_)) => None,
+                    Ok(Message::Close(frame)) => {
+                        info!("WebSocket closed by peer: {:?}", frame);
+                        None
+                    }
+                    Err(e) => Some(Err(anyhow::anyhow!("Failed to connect WebSocket: {}", e))),
+                    _ => None,
                 }
-                Ok(Message::Ping(_) | Message::Pong(_)) => None,
-                Ok(Message::Close(frame)) => {
-                    info!("WebSocket closed by peer: {:?}", frame);
-                    None
-                }
-                Err(e) => Some(Err(anyhow::anyhow!("Failed to connect WebSocket: {}", e))),
-                _ => None,
             }
         });
 
         Ok(stream)
     }
+    pub fn connect_resilient(self) -> impl Stream<Item = KlineEvent> {
+        let max_backoff = Duration::from_secs(30);
+        let state = ResilientState {
+            client: self,
+            stream: None,
+            backoff: Duration::from_secs(1),
+            connected: false,
+        };
+
+        stream::unfold(state, move |mut state| async move {
+            loop {
+                let Some(stream) = state.stream.as_mut() else {
+                    match state.client.connect().await {
+                        Ok(s) => {
+                            state.stream = Some(s.boxed());
+                            state.backoff = Duration::from_secs(1);
+
+                            if state.connected {
+                                METRICS.record_ws_reconnect();
+                                return Some((KlineEvent::Reconnected, state));
+                            }
+
+                            state.connected = true;
+                        }
+                        Err(e) => {
+                            warn!("WebSocket connection failed: {}", e);
+                            sleep(state.backoff).await;
+                            state.backoff = std::cmp::min(state.backoff * 2, max_backoff);
+                        }
+                    }
+                    continue;
+                };
+
+                match stream.next().await {
+                    Some(Ok(candle)) => return Some((KlineEvent::Candle(candle), state)),
+                    Some(Err(e)) => warn!("WebSocket stream error: {}", e),
+                    None => warn!("WebSocket stream ended"),
+                }
+
+                state.stream = None;
+                warn!("Reconnecting WebSocket in {:?}...", state.backoff);
+                sleep(state.backoff).await;
+                state.backoff = std::cmp::min(state.backoff * 2, max_backoff);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline_json(closed: bool) -> String {
+        format!(
+            r#"{{"e":"kline","E":1700000060000,"s":"ETHUSDT","k":{{"t":1700000000000,"o":"2000.1","h":"2010","l":"1995.5","c":"2005","v":"12.3","x":{}}}}}"#,
+            closed
+        )
+    }
+
+    #[test]
+    fn parse_kline_event_raw_stream() {
+        let event = WebSocketClient::parse_kline_event(&kline_json(true))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(event.symbol, "ETHUSDT");
+        assert_eq!(event.kline.open_time, 1_700_000_000_000);
+        assert_eq!(event.kline.close, "2005");
+        assert!(event.kline.is_closed);
+    }
+
+    #[test]
+    fn parse_kline_event_combined_stream() {
+        let text = format!(
+            r#"{{"stream":"ethusdt@kline_1m","data":{}}}"#,
+            kline_json(true)
+        );
+        let event = WebSocketClient::parse_kline_event(&text).unwrap().unwrap();
+
+        assert_eq!(event.symbol, "ETHUSDT");
+        assert_eq!(event.kline.high, "2010");
+    }
+
+    #[test]
+    fn parse_kline_event_skips_open_klines() {
+        assert!(WebSocketClient::parse_kline_event(&kline_json(false))
+            .unwrap()
+            .is_none());
+
+        let text = format!(
+            r#"{{"stream":"ethusdt@kline_1m","data":{}}}"#,
+            kline_json(false)
+        );
+        assert!(WebSocketClient::parse_kline_event(&text).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_kline_event_ignores_other_events() {
+        let text = r#"{"e":"trade","E":1700000060000,"s":"ETHUSDT"}"#;
+
+        assert!(WebSocketClient::parse_kline_event(text).unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_kline_event_rejects_combined_without_data() {
+        let text = r#"{"stream":"ethusdt@kline_1m"}"#;
+
+        assert!(WebSocketClient::parse_kline_event(text).is_err());
+    }
+
+    #[test]
+    fn parse_execution_report_fill() {
+        let text = r#"{"e":"executionReport","s":"ETHUSDT","c":"abc","C":"","S":"SELL","X":"PARTIALLY_FILLED","l":"0.5","L":"2001.5","z":"0.75"}"#;
+        let update = UserDataClient::parse_execution_report(text)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(update.symbol, "ETHUSDT");
+        assert_eq!(update.client_order_id, "abc");
+        assert_eq!(update.side, Side::Sell);
+        assert_eq!(update.status, OrderStatus::PartiallyFilled);
+        assert_eq!(update.last_filled_qty, Decimal::new(5, 1));
+        assert_eq!(update.last_filled_price, Decimal::new(20015, 1));
+        assert_eq!(update.cumulative_filled_qty, Decimal::new(75, 2));
+    }
+
+    #[test]
+    fn parse_execution_report_uses_original_id_on_cancel() {
+        let text = r#"{"e":"executionReport","s":"ETHUSDT","c":"cancel-req","C":"abc","S":"BUY","X":"CANCELED","l":"0","L":"0","z":"0"}"#;
+        let update = UserDataClient::parse_execution_report(text)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(update.client_order_id, "abc");
+        assert_eq!(update.side, Side::Buy);
+        assert_eq!(update.status, OrderStatus::Canceled);
+    }
+
+    #[test]
+    fn parse_execution_report_ignores_other_events() {
+        let text = r#"{"e":"outboundAccountPosition","E":1700000060000}"#;
+
+        assert!(UserDataClient::parse_execution_report(text)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_execution_report_rejects_unknown_side() {
+        let text = r#"{"e":"executionReport","s":"ETHUSDT","c":"abc","S":"HOLD","X":"NEW","l":"0","L":"0","z":"0"}"#;
+
+        assert!(UserDataClient::parse_execution_report(text).is_err());
+    }
 }